@@ -0,0 +1,202 @@
+//! Run many VMs in parallel and summarise how each run ended, for triaging
+//! which ROMs a core change breaks.
+//!
+//! Each `Job` is one ROM on one platform, quirk set and RNG seed. `run_batch`
+//! spreads jobs over the current rayon thread pool and returns one `Summary`
+//! per job, in job order.
+
+use chip8_core::events::Event;
+use chip8_core::platform::Platform;
+use chip8_core::quirks::Quirks;
+use chip8_core::rom::RomInfo;
+use chip8_core::vm::{Chip8VM, DEFAULT_CYCLES_PER_FRAME};
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// A ROM image shared by every job that runs it.
+pub struct Rom {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    pub info: RomInfo,
+}
+
+impl Rom {
+    pub fn new(name: String, bytes: Vec<u8>) -> Rom {
+        let info = RomInfo::new(&bytes);
+        Rom { name, bytes, info }
+    }
+}
+
+pub struct Job<'a> {
+    pub rom: &'a Rom,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// How the quirks were chosen, copied into the summary.
+    pub quirks_name: String,
+    pub seed: u64,
+}
+
+/// How long each job runs for.
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub frames: u32,
+    pub cycles_per_frame: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        // 10 seconds.
+        Settings {
+            frames: 600,
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+        }
+    }
+}
+
+/// How a job ended.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub rom: String,
+    pub sha1: String,
+    pub platform: &'static str,
+    pub quirks: String,
+    pub seed: u64,
+    /// Frames run, fewer than requested if the VM halted or faulted.
+    pub frames: u32,
+    /// Instructions executed, leaving out cycles spent waiting for a key or
+    /// the vblank.
+    pub instructions: u64,
+    pub pc: u16,
+    /// `Display::frame_hash` of the last frame, as hex.
+    pub frame_hash: String,
+    /// The ROM jumped to itself, which is how most programs stop.
+    pub halted: bool,
+    /// The error that stopped the VM, if any.
+    pub fault: Option<String>,
+}
+
+/// Run one job to completion on the calling thread.
+pub fn run_job(job: &Job, settings: &Settings) -> Summary {
+    let mut vm = Chip8VM::new();
+    vm.seed_rng(job.seed);
+    vm.set_platform(job.platform)
+        .expect("platform is set before the ROM is loaded");
+    vm.set_quirks(job.quirks);
+
+    let mut frames = 0;
+    let mut halted = false;
+    let mut fault = vm
+        .load_rom_bytes(&job.rom.bytes)
+        .err()
+        .map(|e| e.to_string());
+    if fault.is_none() {
+        'frames: while frames < settings.frames {
+            for _ in 0..settings.cycles_per_frame {
+                if let Err(e) = vm.cycle() {
+                    fault = Some(e.to_string());
+                    break 'frames;
+                }
+            }
+            vm.tick_timers();
+            frames += 1;
+            while let Some(event) = vm.poll_event() {
+                halted |= matches!(event, Event::Halted { .. });
+            }
+            // Nothing can break a jump to itself, so stop early.
+            if halted {
+                break;
+            }
+        }
+    }
+
+    Summary {
+        rom: job.rom.name.clone(),
+        sha1: job.rom.info.sha1_hex().to_string(),
+        platform: job.platform.name(),
+        quirks: job.quirks_name.clone(),
+        seed: job.seed,
+        frames,
+        instructions: vm.instruction_count(),
+        pc: vm.pc(),
+        frame_hash: format!("{:016x}", vm.display().frame_hash()),
+        halted,
+        fault,
+    }
+}
+
+/// Run every job on the current rayon thread pool.
+pub fn run_batch(jobs: &[Job], settings: &Settings) -> Vec<Summary> {
+    jobs.par_iter().map(|job| run_job(job, settings)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_rom(name: &str) -> Rom {
+        let path = format!("{}/../bin/test-roms/{}", env!("CARGO_MANIFEST_DIR"), name);
+        Rom::new(name.to_string(), fs::read(path).unwrap())
+    }
+
+    fn job(rom: &Rom, platform: Platform) -> Job<'_> {
+        Job {
+            rom,
+            platform,
+            quirks: platform.quirks(),
+            quirks_name: "platform".to_string(),
+            seed: 0,
+        }
+    }
+
+    #[test]
+    fn test_run_job_halted() {
+        let rom = test_rom("3-corax+.ch8");
+        // One cycle a frame so both runs stop right after the first jump to
+        // itself.
+        let settings = Settings {
+            frames: 10_000,
+            cycles_per_frame: 1,
+        };
+        let vip = run_job(&job(&rom, Platform::CosmacVip), &settings);
+        assert!(vip.halted);
+        assert_eq!(vip.fault, None);
+        assert!(vip.frames < settings.frames);
+
+        // Waiting on the vblank spends cycles but runs no extra instructions.
+        let mut no_vblank = job(&rom, Platform::CosmacVip);
+        no_vblank.quirks.vblank = false;
+        let no_vblank = run_job(&no_vblank, &settings);
+        assert!(no_vblank.halted);
+        assert_eq!(no_vblank.instructions, vip.instructions);
+
+        // At full speed the draws leave cycles idle until the next frame.
+        let settings = Settings::default();
+        let vip = run_job(&job(&rom, Platform::CosmacVip), &settings);
+        assert!(vip.halted);
+        assert!(vip.instructions < u64::from(vip.frames * settings.cycles_per_frame));
+    }
+
+    #[test]
+    fn test_run_job_fault() {
+        let rom = Rom::new("bad".to_string(), vec![0xFF, 0xFF]);
+        let summary = run_job(&job(&rom, Platform::CosmacVip), &Settings::default());
+        assert!(summary.fault.is_some());
+        assert!(!summary.halted);
+        assert_eq!(summary.frames, 0);
+        assert_eq!(summary.instructions, 0);
+    }
+
+    #[test]
+    fn test_run_job_frame_hash() {
+        let rom = test_rom("ibm_logo.ch8");
+        let first = run_job(&job(&rom, Platform::CosmacVip), &Settings::default());
+        let second = run_job(&job(&rom, Platform::CosmacVip), &Settings::default());
+        assert!(first.halted);
+        assert_eq!(first.frame_hash, second.frame_hash);
+
+        let blank = Rom::new("blank".to_string(), vec![0x12, 0x00]);
+        let blank = run_job(&job(&blank, Platform::CosmacVip), &Settings::default());
+        assert_ne!(first.frame_hash, blank.frame_hash);
+    }
+}
@@ -0,0 +1,229 @@
+use chip8_batch::{run_batch, Job, Rom, Settings};
+use chip8_core::platform::Platform;
+use chip8_core::quirks::Quirks;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+const ROM_EXTENSIONS: [&str; 3] = ["ch8", "c8", "sc8"];
+
+const USAGE: &str = "Usage: chip8-batch [options] <rom or directory>...
+
+Options:
+  --frames <n>            60Hz frames to run each job for (default 600)
+  --cycles-per-frame <n>  CPU cycles per frame (default 8, 480Hz)
+  --platforms <list>      comma separated platforms to run on (default vip)
+  --quirks <overrides>    a quirk set as name=0|1 pairs separated by commas,
+                          applied over each platform's quirks, e.g.
+                          memory-increment-by-x=1,vblank=0. Repeat for more
+                          sets, each one a separate job
+  --seeds <seeds>         RNG seeds, as a list (1,2,3) or range (0..100)
+  --threads <n>           worker threads (default: one per core)
+  --format <json|csv>     output format (default json)";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Csv,
+}
+
+struct Args {
+    paths: Vec<PathBuf>,
+    settings: Settings,
+    platforms: Vec<Platform>,
+    // Label and overrides of each quirk set.
+    quirk_sets: Vec<(String, Vec<(String, bool)>)>,
+    seeds: Vec<u64>,
+    threads: usize,
+    format: Format,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args = match parse_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let roms = collect_roms(&args.paths)?;
+
+    let mut jobs = Vec::new();
+    for rom in &roms {
+        for &platform in &args.platforms {
+            for (quirks_name, overrides) in &args.quirk_sets {
+                let quirks = apply_overrides(platform.quirks(), overrides)?;
+                for &seed in &args.seeds {
+                    jobs.push(Job {
+                        rom,
+                        platform,
+                        quirks,
+                        quirks_name: quirks_name.clone(),
+                        seed,
+                    });
+                }
+            }
+        }
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()?;
+    let summaries = pool.install(|| run_batch(&jobs, &args.settings));
+
+    let stdout = io::stdout().lock();
+    match args.format {
+        Format::Json => {
+            serde_json::to_writer_pretty(stdout, &summaries)?;
+            println!();
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(stdout);
+            for summary in &summaries {
+                writer.serialize(summary)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut parsed = Args {
+        paths: Vec::new(),
+        settings: Settings::default(),
+        platforms: vec![Platform::default()],
+        quirk_sets: Vec::new(),
+        seeds: vec![0],
+        // 0 lets rayon pick.
+        threads: 0,
+        format: Format::Json,
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--frames" => parsed.settings.frames = parse_number(value()?)?,
+            "--cycles-per-frame" => parsed.settings.cycles_per_frame = parse_number(value()?)?,
+            "--platforms" => {
+                parsed.platforms = value()?
+                    .split(',')
+                    .map(|name| {
+                        Platform::parse(name).ok_or_else(|| format!("Unknown platform: {}", name))
+                    })
+                    .collect::<Result<_, _>>()?;
+            }
+            "--quirks" => {
+                let set = value()?;
+                let overrides = parse_quirk_set(set)?;
+                // Catch unknown names before any ROMs are read.
+                apply_overrides(Quirks::default(), &overrides)?;
+                parsed.quirk_sets.push((set.clone(), overrides));
+            }
+            "--seeds" => parsed.seeds = parse_seeds(value()?)?,
+            "--threads" => parsed.threads = parse_number(value()?)?,
+            "--format" => {
+                parsed.format = match value()?.as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("Unknown format: {}", other)),
+                }
+            }
+            _ if !arg.starts_with("--") => parsed.paths.push(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    if parsed.paths.is_empty() {
+        return Err("Missing ROM path".to_string());
+    }
+    if parsed.quirk_sets.is_empty() {
+        parsed.quirk_sets.push(("platform".to_string(), Vec::new()));
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("Invalid number: {}", s))
+}
+
+fn parse_seeds(s: &str) -> Result<Vec<u64>, String> {
+    if let Some((start, end)) = s.split_once("..") {
+        let (start, end): (u64, u64) = (parse_number(start)?, parse_number(end)?);
+        if start >= end {
+            return Err(format!("Empty seed range: {}", s));
+        }
+        return Ok((start..end).collect());
+    }
+    s.split(',').map(parse_number).collect()
+}
+
+fn parse_quirk_set(s: &str) -> Result<Vec<(String, bool)>, String> {
+    s.split(',')
+        .map(|pair| {
+            let (name, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected name=0|1: {}", pair))?;
+            let value = match value {
+                "0" | "false" => false,
+                "1" | "true" => true,
+                _ => return Err(format!("Expected 0 or 1 for {}", name)),
+            };
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+fn apply_overrides(mut quirks: Quirks, overrides: &[(String, bool)]) -> Result<Quirks, String> {
+    for (name, value) in overrides {
+        *quirks
+            .get_mut(name)
+            .ok_or_else(|| format!("Unknown quirk: {}", name))? = *value;
+    }
+    Ok(quirks)
+}
+
+/// Read every ROM given directly, plus the ROMs directly inside any
+/// directories, sorted by path.
+fn collect_roms(paths: &[PathBuf]) -> io::Result<Vec<Rom>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<_>>()?;
+            entries.retain(|p| p.is_file() && has_rom_extension(p));
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+        .into_iter()
+        .map(|path| {
+            let bytes = fs::read(&path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            Ok(Rom::new(path.display().to_string(), bytes))
+        })
+        .collect()
+}
+
+fn has_rom_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ROM_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
@@ -0,0 +1,52 @@
+/*
+ * Runs a ROM for a few seconds through the C API and prints the screen.
+ *
+ *   cargo build --release
+ *   cc -Iinclude -o smoke examples/smoke.c target/release/libchip8.a -lpthread -ldl -lm
+ *   ./smoke ../bin/test-roms/ibm_logo.ch8
+ */
+#include <stdio.h>
+
+#include "chip8.h"
+
+int main(int argc, char **argv) {
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <rom.ch8>\n", argv[0]);
+        return 2;
+    }
+
+    FILE *f = fopen(argv[1], "rb");
+    if (!f) {
+        perror("fopen");
+        return 1;
+    }
+    static uint8_t rom[CHIP8_MEMORY_SIZE];
+    size_t rom_size = fread(rom, 1, sizeof(rom), f);
+    fclose(f);
+
+    Chip8 *chip8 = chip8_new();
+    Chip8Status status = chip8_load_rom(chip8, rom, rom_size);
+    for (int frame = 0; status == CHIP8_STATUS_OK && frame < 180; frame++) {
+        status = chip8_run_frame(chip8, 8);
+    }
+    if (status != CHIP8_STATUS_OK) {
+        fprintf(stderr, "error: %s\n", chip8_status_message(status));
+        chip8_free(chip8);
+        return 1;
+    }
+
+    static uint8_t pixels[CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT];
+    chip8_framebuffer(chip8, pixels, sizeof(pixels));
+    for (int y = 0; y < CHIP8_DISPLAY_HEIGHT; y++) {
+        for (int x = 0; x < CHIP8_DISPLAY_WIDTH; x++) {
+            putchar(pixels[y * CHIP8_DISPLAY_WIDTH + x] ? '#' : ' ');
+        }
+        putchar('\n');
+    }
+
+    Chip8Registers regs;
+    chip8_registers(chip8, &regs);
+    printf("PC=%03X I=%03X\n", regs.pc, regs.i);
+    chip8_free(chip8);
+    return 0;
+}
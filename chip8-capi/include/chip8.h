@@ -0,0 +1,167 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from chip8-capi, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define CHIP8_DISPLAY_WIDTH 64
+
+#define CHIP8_DISPLAY_HEIGHT 32
+
+#define CHIP8_MEMORY_SIZE 4096
+
+#define CHIP8_MAX_STACK_DEPTH 16
+
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_NULL_POINTER,
+  CHIP8_STATUS_BUFFER_TOO_SMALL,
+  CHIP8_STATUS_INVALID_ARGUMENT,
+  CHIP8_STATUS_UNKNOWN_INSTRUCTION,
+  CHIP8_STATUS_UNKNOWN_KEY,
+  CHIP8_STATUS_ROM_LOAD_FAILURE,
+  CHIP8_STATUS_STACK_UNDERFLOW,
+  CHIP8_STATUS_STACK_OVERFLOW,
+  CHIP8_STATUS_INVALID_SAVE_STATE,
+  CHIP8_STATUS_MEMORY_OUT_OF_BOUNDS,
+} Chip8Status;
+
+/**
+ * Opaque handle to a VM.
+ */
+typedef struct Chip8 Chip8;
+
+/**
+ * Snapshot of the CPU registers.
+ */
+typedef struct Chip8Registers {
+  uint8_t v[16];
+  uint16_t pc;
+  uint16_t i;
+  uint8_t delay_timer;
+  uint8_t sound_timer;
+  /**
+   * Number of valid entries in `stack`, oldest first. Only the innermost
+   * `CHIP8_MAX_STACK_DEPTH` calls are reported.
+   */
+  uint8_t stack_depth;
+  uint16_t stack[CHIP8_MAX_STACK_DEPTH];
+} Chip8Registers;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a new VM. Free it with `chip8_free`.
+ */
+struct Chip8 *chip8_new(void);
+
+/**
+ * # Safety
+ * `chip8` must be null or a handle from `chip8_new` that hasn't been freed.
+ */
+void chip8_free(struct Chip8 *chip8);
+
+/**
+ * Load a ROM image at the program start address.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_rom(struct Chip8 *chip8, const uint8_t *data, size_t len);
+
+/**
+ * Execute a single instruction.
+ *
+ * # Safety
+ * `chip8` must be a live handle.
+ */
+enum Chip8Status chip8_step(struct Chip8 *chip8);
+
+/**
+ * Execute `cycles` instructions then tick the timers once, i.e. one 60Hz frame.
+ *
+ * # Safety
+ * `chip8` must be a live handle.
+ */
+enum Chip8Status chip8_run_frame(struct Chip8 *chip8, uint32_t cycles);
+
+/**
+ * Decrement the delay and sound timers, should be called at 60Hz.
+ *
+ * # Safety
+ * `chip8` must be a live handle.
+ */
+enum Chip8Status chip8_tick_timers(struct Chip8 *chip8);
+
+/**
+ * Press or release one of the 16 keys (0x0 - 0xF).
+ *
+ * # Safety
+ * `chip8` must be a live handle.
+ */
+enum Chip8Status chip8_set_key(struct Chip8 *chip8, uint8_t key, bool pressed);
+
+/**
+ * Copy the display into `out` as one byte per pixel (0 or 1), row-major.
+ * `len` must be at least `CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT`.
+ *
+ * # Safety
+ * `out` must point to `len` writable bytes.
+ */
+enum Chip8Status chip8_framebuffer(struct Chip8 *chip8, uint8_t *out, size_t len);
+
+/**
+ * # Safety
+ * `chip8` must be a live handle and `out` writable.
+ */
+enum Chip8Status chip8_registers(const struct Chip8 *chip8, struct Chip8Registers *out);
+
+/**
+ * Copy `len` bytes of memory starting at `addr` into `out`.
+ *
+ * # Safety
+ * `out` must point to `len` writable bytes.
+ */
+enum Chip8Status chip8_read_memory(const struct Chip8 *chip8,
+                                   uint16_t addr,
+                                   uint8_t *out,
+                                   size_t len);
+
+/**
+ * Write a save state into `out`. `written` receives the state's size; if
+ * `len` is too small, `CHIP8_STATUS_BUFFER_TOO_SMALL` is returned and
+ * `written` holds the size needed.
+ *
+ * # Safety
+ * `out` must point to `len` writable bytes, `written` must be writable.
+ */
+enum Chip8Status chip8_save_state(const struct Chip8 *chip8,
+                                  uint8_t *out,
+                                  size_t len,
+                                  size_t *written);
+
+/**
+ * Restore a state written by `chip8_save_state`.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes.
+ */
+enum Chip8Status chip8_load_state(struct Chip8 *chip8, const uint8_t *data, size_t len);
+
+/**
+ * Human readable description of a status code. The string is static.
+ */
+const char *chip8_status_message(enum Chip8Status status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* CHIP8_H */
@@ -0,0 +1,423 @@
+//! C API for embedding `Chip8VM`.
+//!
+//! The header `include/chip8.h` is checked in. `test_header_up_to_date`
+//! fails when it is stale, and `CHIP8_UPDATE_HEADER=1 cargo test` regenerates
+//! it. Every function that can fail returns a `Chip8Status`, with
+//! `CHIP8_STATUS_OK` (0) on success. Passing a null `Chip8` handle is reported as
+//! `CHIP8_STATUS_NULL_POINTER` rather than crashing.
+
+use chip8_core::display::Display;
+use chip8_core::vm::{Chip8VM, VMError};
+use std::ffi::c_char;
+use std::slice;
+
+pub const CHIP8_DISPLAY_WIDTH: usize = 64;
+pub const CHIP8_DISPLAY_HEIGHT: usize = 32;
+pub const CHIP8_MEMORY_SIZE: usize = 4096;
+pub const CHIP8_MAX_STACK_DEPTH: usize = 16;
+
+// Literal values above so cbindgen can emit them; keep them in sync with core.
+const _: () = assert!(CHIP8_DISPLAY_WIDTH == Display::WIDTH);
+const _: () = assert!(CHIP8_DISPLAY_HEIGHT == Display::HEIGHT);
+
+/// Opaque handle to a VM.
+pub struct Chip8 {
+    vm: Chip8VM,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    NullPointer,
+    BufferTooSmall,
+    InvalidArgument,
+    UnknownInstruction,
+    UnknownKey,
+    RomLoadFailure,
+    StackUnderflow,
+    StackOverflow,
+    InvalidSaveState,
+    MemoryOutOfBounds,
+}
+
+impl From<&VMError> for Chip8Status {
+    fn from(err: &VMError) -> Self {
+        match err {
+            VMError::UnknownInstruction(_) => Chip8Status::UnknownInstruction,
+            VMError::UnknownKey(_) => Chip8Status::UnknownKey,
+            VMError::InvalidAction(_) | VMError::InvalidMemoryValue { .. } => {
+                Chip8Status::InvalidArgument
+            }
+            VMError::RomLoadFailure(_) | VMError::RomEmpty() | VMError::RomTooLarge { .. } => {
+                Chip8Status::RomLoadFailure
+            }
+            VMError::StackUnderflow() => Chip8Status::StackUnderflow,
+            VMError::StackOverflow() => Chip8Status::StackOverflow,
+            VMError::InvalidSaveState() => Chip8Status::InvalidSaveState,
+            VMError::InvalidFontAddress(_) | VMError::RomAlreadyLoaded() => {
+                Chip8Status::InvalidArgument
+            }
+            VMError::MemoryOutOfBounds { .. } => Chip8Status::MemoryOutOfBounds,
+        }
+    }
+}
+
+fn status(result: Result<(), VMError>) -> Chip8Status {
+    match result {
+        Ok(()) => Chip8Status::Ok,
+        Err(err) => Chip8Status::from(&err),
+    }
+}
+
+/// Snapshot of the CPU registers.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Chip8Registers {
+    pub v: [u8; 16],
+    pub pc: u16,
+    pub i: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// Number of valid entries in `stack`, oldest first. Only the innermost
+    /// `CHIP8_MAX_STACK_DEPTH` calls are reported.
+    pub stack_depth: u8,
+    pub stack: [u16; CHIP8_MAX_STACK_DEPTH],
+}
+
+/// Create a new VM. Free it with `chip8_free`.
+#[no_mangle]
+pub extern "C" fn chip8_new() -> *mut Chip8 {
+    Box::into_raw(Box::new(Chip8 { vm: Chip8VM::new() }))
+}
+
+/// # Safety
+/// `chip8` must be null or a handle from `chip8_new` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Load a ROM image at the program start address.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    chip8: *mut Chip8,
+    data: *const u8,
+    len: usize,
+) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_mut(), data.is_null()) else {
+        return Chip8Status::NullPointer;
+    };
+    status(
+        chip8
+            .vm
+            .load_rom_bytes(slice::from_raw_parts(data, len))
+            .map(|_| ()),
+    )
+}
+
+/// Execute a single instruction.
+///
+/// # Safety
+/// `chip8` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(chip8: *mut Chip8) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    status(chip8.vm.cycle())
+}
+
+/// Execute `cycles` instructions then tick the timers once, i.e. one 60Hz frame.
+///
+/// # Safety
+/// `chip8` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(chip8: *mut Chip8, cycles: u32) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    for _ in 0..cycles {
+        if let Err(err) = chip8.vm.cycle() {
+            return Chip8Status::from(&err);
+        }
+    }
+    chip8.vm.tick_timers();
+    Chip8Status::Ok
+}
+
+/// Decrement the delay and sound timers, should be called at 60Hz.
+///
+/// # Safety
+/// `chip8` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(chip8: *mut Chip8) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    chip8.vm.tick_timers();
+    Chip8Status::Ok
+}
+
+/// Press or release one of the 16 keys (0x0 - 0xF).
+///
+/// # Safety
+/// `chip8` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8, pressed: bool) -> Chip8Status {
+    let Some(chip8) = chip8.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if key > 0xF {
+        return Chip8Status::UnknownKey;
+    }
+    chip8.vm.handle_key(key, pressed);
+    Chip8Status::Ok
+}
+
+/// Copy the display into `out` as one byte per pixel (0 or 1), row-major.
+/// `len` must be at least `CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT`.
+///
+/// # Safety
+/// `out` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(
+    chip8: *mut Chip8,
+    out: *mut u8,
+    len: usize,
+) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_mut(), out.is_null()) else {
+        return Chip8Status::NullPointer;
+    };
+    let frame = chip8.vm.get_frame_buffer();
+    if len < frame.len() {
+        return Chip8Status::BufferTooSmall;
+    }
+    let out = slice::from_raw_parts_mut(out, frame.len());
+    for (o, pixel) in out.iter_mut().zip(frame) {
+        *o = *pixel as u8;
+    }
+    Chip8Status::Ok
+}
+
+/// # Safety
+/// `chip8` must be a live handle and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_registers(
+    chip8: *const Chip8,
+    out: *mut Chip8Registers,
+) -> Chip8Status {
+    let (Some(chip8), Some(out)) = (chip8.as_ref(), out.as_mut()) else {
+        return Chip8Status::NullPointer;
+    };
+    let vm = &chip8.vm;
+    let mut regs = Chip8Registers {
+        v: *vm.registers(),
+        pc: vm.pc(),
+        i: vm.index_register(),
+        delay_timer: vm.delay_timer(),
+        sound_timer: vm.sound_timer(),
+        ..Default::default()
+    };
+    let stack = vm.call_stack();
+    let depth = stack.len().min(CHIP8_MAX_STACK_DEPTH);
+    regs.stack[..depth].copy_from_slice(&stack[stack.len() - depth..]);
+    regs.stack_depth = depth as u8;
+    *out = regs;
+    Chip8Status::Ok
+}
+
+/// Copy `len` bytes of memory starting at `addr` into `out`.
+///
+/// # Safety
+/// `out` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_read_memory(
+    chip8: *const Chip8,
+    addr: u16,
+    out: *mut u8,
+    len: usize,
+) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_ref(), out.is_null()) else {
+        return Chip8Status::NullPointer;
+    };
+    let memory = chip8.vm.memory();
+    let range = (addr as usize)
+        .checked_add(len)
+        .map(|end| addr as usize..end);
+    let Some(src) = range.and_then(|range| memory.get(range)) else {
+        return Chip8Status::InvalidArgument;
+    };
+    slice::from_raw_parts_mut(out, len).copy_from_slice(src);
+    Chip8Status::Ok
+}
+
+/// Write a save state into `out`. `written` receives the state's size; if
+/// `len` is too small, `CHIP8_STATUS_BUFFER_TOO_SMALL` is returned and
+/// `written` holds the size needed.
+///
+/// # Safety
+/// `out` must point to `len` writable bytes, `written` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(
+    chip8: *const Chip8,
+    out: *mut u8,
+    len: usize,
+    written: *mut usize,
+) -> Chip8Status {
+    let (Some(chip8), Some(written)) = (chip8.as_ref(), written.as_mut()) else {
+        return Chip8Status::NullPointer;
+    };
+    let state = chip8.vm.save_state();
+    *written = state.len();
+    if out.is_null() || len < state.len() {
+        return Chip8Status::BufferTooSmall;
+    }
+    slice::from_raw_parts_mut(out, state.len()).copy_from_slice(&state);
+    Chip8Status::Ok
+}
+
+/// Restore a state written by `chip8_save_state`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    chip8: *mut Chip8,
+    data: *const u8,
+    len: usize,
+) -> Chip8Status {
+    let (Some(chip8), false) = (chip8.as_mut(), data.is_null()) else {
+        return Chip8Status::NullPointer;
+    };
+    status(chip8.vm.load_state(slice::from_raw_parts(data, len)))
+}
+
+/// Human readable description of a status code. The string is static.
+#[no_mangle]
+pub extern "C" fn chip8_status_message(status: Chip8Status) -> *const c_char {
+    let msg = match status {
+        Chip8Status::Ok => c"ok",
+        Chip8Status::NullPointer => c"null pointer",
+        Chip8Status::BufferTooSmall => c"buffer too small",
+        Chip8Status::InvalidArgument => c"invalid argument",
+        Chip8Status::UnknownInstruction => c"unknown instruction",
+        Chip8Status::UnknownKey => c"unknown key",
+        Chip8Status::RomLoadFailure => c"rom load failure",
+        Chip8Status::StackUnderflow => c"stack underflow",
+        Chip8Status::StackOverflow => c"stack overflow",
+        Chip8Status::InvalidSaveState => c"invalid save state",
+        Chip8Status::MemoryOutOfBounds => c"memory access out of bounds",
+    };
+    msg.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn test_run_rom() {
+        unsafe {
+            let chip8 = chip8_new();
+            // V0 = 0x2A, I = 0x300, store V0, loop forever
+            let rom = [0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06];
+            assert_eq!(
+                chip8_load_rom(chip8, rom.as_ptr(), rom.len()),
+                Chip8Status::Ok
+            );
+            assert_eq!(chip8_run_frame(chip8, 4), Chip8Status::Ok);
+
+            let mut regs = Chip8Registers::default();
+            assert_eq!(chip8_registers(chip8, &mut regs), Chip8Status::Ok);
+            assert_eq!(regs.v[0], 0x2A);
+            assert_eq!(regs.i, 0x300);
+            assert_eq!(regs.pc, 0x206);
+
+            let mut byte = 0;
+            assert_eq!(
+                chip8_read_memory(chip8, 0x300, &mut byte, 1),
+                Chip8Status::Ok
+            );
+            assert_eq!(byte, 0x2A);
+            assert_eq!(
+                chip8_read_memory(chip8, 0xFFF, &mut byte, 2),
+                Chip8Status::InvalidArgument
+            );
+            assert_eq!(
+                chip8_read_memory(chip8, 0x300, &mut byte, usize::MAX),
+                Chip8Status::InvalidArgument
+            );
+            chip8_free(chip8);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            assert_eq!(chip8_step(ptr::null_mut()), Chip8Status::NullPointer);
+            let chip8 = chip8_new();
+            // 0xFFFF doesn't decode
+            let rom = [0xFF, 0xFF];
+            chip8_load_rom(chip8, rom.as_ptr(), rom.len());
+            assert_eq!(chip8_step(chip8), Chip8Status::UnknownInstruction);
+            assert_eq!(chip8_set_key(chip8, 0x10, true), Chip8Status::UnknownKey);
+
+            let mut frame = [0u8; 8];
+            assert_eq!(
+                chip8_framebuffer(chip8, frame.as_mut_ptr(), frame.len()),
+                Chip8Status::BufferTooSmall
+            );
+
+            let mut needed = 0;
+            assert_eq!(
+                chip8_save_state(chip8, ptr::null_mut(), 0, &mut needed),
+                Chip8Status::BufferTooSmall
+            );
+            let mut state = vec![0u8; needed];
+            assert_eq!(
+                chip8_save_state(chip8, state.as_mut_ptr(), state.len(), &mut needed),
+                Chip8Status::Ok
+            );
+            assert_eq!(
+                chip8_load_state(chip8, state.as_ptr(), state.len()),
+                Chip8Status::Ok
+            );
+            assert_eq!(
+                chip8_load_state(chip8, state.as_ptr(), 3),
+                Chip8Status::InvalidSaveState
+            );
+            chip8_free(chip8);
+        }
+    }
+
+    // The header is checked in so builds never write to the source tree.
+    // Regenerate it with `CHIP8_UPDATE_HEADER=1 cargo test` after changing
+    // the API.
+    #[test]
+    fn test_header_up_to_date() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_crate(crate_dir)
+            .with_config(cbindgen::Config::from_root_or_default(crate_dir))
+            .generate()
+            .expect("unable to generate C bindings")
+            .write(&mut generated);
+        let path = std::path::Path::new(crate_dir).join("include/chip8.h");
+        if std::env::var_os("CHIP8_UPDATE_HEADER").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let committed = std::fs::read(&path).unwrap();
+        assert!(
+            committed == generated,
+            "include/chip8.h is out of date, run CHIP8_UPDATE_HEADER=1 cargo test"
+        );
+    }
+}
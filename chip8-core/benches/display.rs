@@ -0,0 +1,65 @@
+use chip8_core::palette::Palette;
+use chip8_core::quirks::Quirks;
+use chip8_core::render::{Renderer, Scaler};
+use chip8_core::vm::Chip8VM;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// Draws the 15 row "0" glyph, moves right and down a little and loops, so
+// almost every other instruction is a DXYN that wraps now and then.
+const DRAW_LOOP: [u8; 14] = [
+    0x60, 0x00, // V0 = 0
+    0x61, 0x00, // V1 = 0
+    0xA0, 0x50, // I = font
+    0xD0, 0x1F, // draw 15 rows at (V0, V1)
+    0x70, 0x03, // V0 += 3
+    0x71, 0x01, // V1 += 1
+    0x12, 0x06, // jump to the draw
+];
+
+fn draw_vm(wrap: bool) -> Chip8VM {
+    let mut vm = Chip8VM::new();
+    vm.set_quirks(Quirks {
+        wrap,
+        ..Quirks::default()
+    });
+    vm.load_rom_bytes(&DRAW_LOOP).unwrap();
+    vm
+}
+
+fn bench_draw(c: &mut Criterion) {
+    for (name, wrap) in [("draw_wrap", true), ("draw_clip", false)] {
+        let mut vm = draw_vm(wrap);
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..1000 {
+                    vm.cycle().unwrap();
+                }
+            })
+        });
+    }
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let mut vm = draw_vm(true);
+    let mut renderer = Renderer::new(Palette::default(), Scaler::default());
+    c.bench_function("frame_buffer", |b| {
+        b.iter(|| {
+            // Draw so every call has stale rows to unpack.
+            for _ in 0..8 {
+                vm.cycle().unwrap();
+            }
+            black_box(vm.get_frame_buffer());
+        })
+    });
+    c.bench_function("render_rgba", |b| {
+        b.iter(|| {
+            for _ in 0..8 {
+                vm.cycle().unwrap();
+            }
+            black_box(renderer.render(vm.get_frame_buffer()));
+        })
+    });
+}
+
+criterion_group!(benches, bench_draw, bench_frame);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+#![no_main]
+
+use chip8_core::instructions::Instruction;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for op in data.chunks_exact(2) {
+        let instr = Instruction::decode(u16::from_be_bytes([op[0], op[1]]));
+        // The disassembler sees the same opcodes, so it must not panic either.
+        let _ = instr.to_string();
+    }
+});
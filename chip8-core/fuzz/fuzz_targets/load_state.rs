@@ -0,0 +1,19 @@
+#![no_main]
+
+use chip8_core::vm::Chip8VM;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut vm = Chip8VM::new();
+    if vm.load_state(data).is_err() {
+        return;
+    }
+    // An accepted state must be runnable.
+    for _ in 0..256 {
+        if vm.cycle().is_err() {
+            break;
+        }
+    }
+    vm.tick_timers();
+    let _ = vm.save_state();
+});
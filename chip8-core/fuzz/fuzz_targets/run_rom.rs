@@ -0,0 +1,85 @@
+#![no_main]
+
+use chip8_core::fault::FaultPolicy;
+use chip8_core::memory::{StackMode, VIP_STACK_ADDRESS};
+use chip8_core::platform::Platform;
+use chip8_core::quirks::Quirks;
+use chip8_core::vm::Chip8VM;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+const MAX_STEPS: usize = 4096;
+// Instructions per 60Hz timer tick.
+const CYCLES_PER_TICK: usize = 8;
+
+#[derive(Arbitrary, Debug)]
+struct KeyEvent {
+    step: u16,
+    key: u8,
+    pressed: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    platform: u8,
+    fault_policy: u8,
+    quirks: [bool; 7],
+    in_memory_stack: bool,
+    seed: u64,
+    steps: u16,
+    keys: Vec<KeyEvent>,
+    rom: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    let platform = Platform::ALL[input.platform as usize % Platform::ALL.len()];
+    let fault_policy = match input.fault_policy % 3 {
+        0 => FaultPolicy::Wrap,
+        1 => FaultPolicy::Fault,
+        _ => FaultPolicy::Ignore,
+    };
+    let [shift, memory_increment_by_x, memory_leave_i_unchanged, wrap, jump, vblank, logic] =
+        input.quirks;
+
+    let mut vm = Chip8VM::new();
+    vm.seed_rng(input.seed);
+    vm.set_platform(platform)
+        .expect("platform is set before the ROM is loaded");
+    vm.set_quirks(Quirks {
+        shift,
+        memory_increment_by_x,
+        memory_leave_i_unchanged,
+        wrap,
+        jump,
+        vblank,
+        logic,
+    });
+    vm.set_fault_policy(fault_policy);
+    if input.in_memory_stack {
+        vm.set_stack_mode(StackMode::Memory {
+            address: VIP_STACK_ADDRESS,
+        });
+    }
+    if vm.load_rom_bytes(&input.rom).is_err() {
+        return;
+    }
+
+    for step in 0..input.steps as usize % MAX_STEPS {
+        // Key codes are deliberately unchecked, bad ones must be ignored.
+        for event in input.keys.iter().filter(|e| e.step as usize == step) {
+            vm.handle_key(event.key, event.pressed);
+        }
+        if step % CYCLES_PER_TICK == 0 {
+            vm.tick_timers();
+        }
+        // Errors are fine, panics are not.
+        if vm.cycle().is_err() {
+            break;
+        }
+    }
+
+    // Whatever state the program reached must survive a save state round trip.
+    let state = vm.save_state();
+    vm.load_state(&state).expect("save state round trip");
+    assert_eq!(vm.save_state(), state);
+});
@@ -0,0 +1,233 @@
+//! Decoder for Octo "cartridge" GIFs.
+//!
+//! Octo hides its save data in the image: every pixel's palette index carries
+//! one nybble in its low 4 bits, two pixels per byte with the high nybble
+//! first, across all frames in order. The bytes are a 32-bit big-endian
+//! length followed by that many bytes of UTF-8 JSON:
+//!
+//! ```json
+//! {"program": "<Octo source>", "options": {"tickrate": 20, "shiftQuirks": false, ...}}
+//! ```
+//!
+//! Carts store Octo assembly rather than a compiled image;
+//! [`Cartridge::assemble`] runs it through [`crate::octo`] to get ROM bytes.
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::palette::{parse_color, Palette};
+use crate::quirks::Quirks;
+use crate::vm::Chip8VM;
+use rand::RngCore;
+
+#[derive(Error, Debug)]
+pub enum CartError {
+    #[error("Not a readable GIF: {0}")]
+    Gif(#[from] gif::DecodingError),
+
+    #[error("Cartridge data is truncated")]
+    Truncated(),
+
+    #[error("Cartridge payload is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Cartridge has no program")]
+    MissingProgram(),
+
+    #[error("Cartridge program doesn't assemble: {0}")]
+    Assemble(#[from] crate::octo::AssembleError),
+}
+
+/// Octo's runtime options as stored in a cart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartOptions {
+    /// Instructions per 60Hz frame.
+    pub tickrate: Option<u32>,
+    /// Background, fill, fill 2 and blend colors.
+    pub palette: Option<Palette>,
+    pub quirks: Quirks,
+}
+
+impl CartOptions {
+    fn from_json(options: &Map<String, Value>) -> CartOptions {
+        let flag = |name: &str| options.get(name).and_then(Value::as_bool);
+        let mut quirks = Quirks::default();
+        let fields = [
+            ("shiftQuirks", &mut quirks.shift),
+            ("loadStoreQuirks", &mut quirks.memory_leave_i_unchanged),
+            ("jumpQuirks", &mut quirks.jump),
+            ("vBlankQuirks", &mut quirks.vblank),
+            ("logicQuirks", &mut quirks.logic),
+        ];
+        for (name, field) in fields {
+            if let Some(value) = flag(name) {
+                *field = value;
+            }
+        }
+        if let Some(clip) = flag("clipQuirks") {
+            quirks.wrap = !clip;
+        }
+
+        let color = |name: &str| {
+            options
+                .get(name)
+                .and_then(Value::as_str)
+                .and_then(parse_color)
+        };
+        let palette = (|| {
+            Some(Palette {
+                colors: [
+                    color("backgroundColor")?,
+                    color("fillColor")?,
+                    color("fillColor2")?,
+                    color("blendColor")?,
+                ],
+            })
+        })();
+
+        CartOptions {
+            tickrate: options
+                .get("tickrate")
+                .and_then(Value::as_u64)
+                .map(|t| t as u32),
+            palette,
+            quirks,
+        }
+    }
+
+    /// Set the cart's quirks on the VM. Tick rate and palette belong to the
+    /// frontend, which has to apply them itself.
+    pub fn apply<R: RngCore>(&self, vm: &mut Chip8VM<R>) {
+        vm.set_quirks(self.quirks);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cartridge {
+    /// Octo assembly source.
+    pub program: String,
+    pub options: CartOptions,
+}
+
+impl Cartridge {
+    pub fn decode(gif_bytes: &[u8]) -> Result<Cartridge, CartError> {
+        let payload = read_payload(gif_bytes)?;
+        let json: Value = serde_json::from_slice(&payload)?;
+        let program = json
+            .get("program")
+            .and_then(Value::as_str)
+            .ok_or(CartError::MissingProgram())?;
+        let empty = Map::new();
+        let options = json
+            .get("options")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+        Ok(Cartridge {
+            program: program.to_string(),
+            options: CartOptions::from_json(options),
+        })
+    }
+
+    /// Assembles the cart's program into a ROM image loaded at 0x200.
+    pub fn assemble(&self) -> Result<Vec<u8>, CartError> {
+        Ok(crate::octo::assemble(&self.program)?)
+    }
+}
+
+fn read_payload(gif_bytes: &[u8]) -> Result<Vec<u8>, CartError> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif_bytes)?;
+
+    let mut nybbles = Vec::new();
+    while let Some(frame) = decoder.read_next_frame()? {
+        nybbles.extend(frame.buffer.iter().map(|index| index & 0xF));
+    }
+    let bytes: Vec<u8> = nybbles
+        .chunks_exact(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect();
+
+    let header = bytes.get(..4).ok_or(CartError::Truncated())?;
+    let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    bytes[4..]
+        .get(..size)
+        .map(<[u8]>::to_vec)
+        .ok_or(CartError::Truncated())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    // Write `payload` into a single-frame GIF the way Octo does.
+    fn build_cart(payload: &str) -> Vec<u8> {
+        let mut data = (payload.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(payload.as_bytes());
+        build_gif(&data)
+    }
+
+    fn build_gif(data: &[u8]) -> Vec<u8> {
+        let mut pixels: Vec<u8> = data.iter().flat_map(|b| [b >> 4, b & 0xF]).collect();
+        let (width, height) = (32, pixels.len().div_ceil(32) as u16);
+        pixels.resize(width as usize * height as usize, 0);
+
+        let palette: Vec<u8> = (0..16).flat_map(|i| [i * 16, i * 16, i * 16]).collect();
+        let mut gif = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut gif, width, height, &palette).unwrap();
+            let frame = gif::Frame {
+                width,
+                height,
+                buffer: Cow::Borrowed(&pixels),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        gif
+    }
+
+    #[test]
+    fn test_decode() {
+        let cart = build_cart(
+            r##"{"program": ": main\n  loop again", "options": {
+                "tickrate": 20, "shiftQuirks": false, "clipQuirks": true, "vBlankQuirks": true,
+                "backgroundColor": "#996600", "fillColor": "#FFCC00",
+                "fillColor2": "#FF6600", "blendColor": "#662200"}}"##,
+        );
+        let cart = Cartridge::decode(&cart).unwrap();
+        assert_eq!(cart.program, ": main\n  loop again");
+        assert_eq!(cart.options.tickrate, Some(20));
+        assert_eq!(cart.options.palette, Some(Palette::OCTO));
+        assert!(!cart.options.quirks.shift);
+        assert!(!cart.options.quirks.wrap);
+        assert!(cart.options.quirks.vblank);
+        // Unset flags keep the VM's defaults.
+        assert!(cart.options.quirks.memory_leave_i_unchanged);
+
+        let mut vm = Chip8VM::new();
+        cart.options.apply(&mut vm);
+        assert_eq!(vm.quirks(), cart.options.quirks);
+        assert_eq!(cart.assemble().unwrap(), [0x12, 0x00]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert!(matches!(
+            Cartridge::decode(b"not a gif"),
+            Err(CartError::Gif(_))
+        ));
+        assert!(matches!(
+            Cartridge::decode(&build_cart(r#"{"options": {}}"#)),
+            Err(CartError::MissingProgram())
+        ));
+        // The length claims more data than the image holds.
+        assert!(matches!(
+            Cartridge::decode(&build_gif(&[0, 0, 1, 0, b'{'])),
+            Err(CartError::Truncated())
+        ));
+        let cart = Cartridge::decode(&build_cart(r#"{"program": "v0 := 1"}"#)).unwrap();
+        assert!(matches!(cart.assemble(), Err(CartError::Assemble(_))));
+    }
+}
@@ -1,5 +1,22 @@
+use crate::events::Rect;
+use crate::state::StateReader;
+#[cfg(feature = "alloc")]
+use crate::state::StateWriter;
+use crate::vm::VMError;
+
 pub struct Display {
-    pixels: [[bool; Display::WIDTH]; Display::HEIGHT],
+    // One u64 per row, bit 63 is the leftmost pixel so sprite bytes line up
+    // with a shift.
+    rows: [u64; Display::HEIGHT],
+    // bool copy of rows for get_frame_buffer, refreshed lazily.
+    view: [bool; Display::WIDTH * Display::HEIGHT],
+    stale_rows: u32,
+    // one bit per row/column changed since the last clear_dirty, columns
+    // laid out like rows.
+    dirty_rows: u32,
+    dirty_columns: u64,
+    // XOR of pixel_key for every lit pixel, kept up to date on each change.
+    hash: u64,
 }
 
 impl Display {
@@ -8,31 +25,162 @@ impl Display {
 
     pub(crate) fn new() -> Display {
         Display {
-            pixels: [[false; Display::WIDTH]; Display::HEIGHT],
+            rows: [0; Display::HEIGHT],
+            view: [false; Display::WIDTH * Display::HEIGHT],
+            stale_rows: 0,
+            dirty_rows: 0,
+            dirty_columns: 0,
+            hash: 0,
         }
     }
 
-    pub(crate) fn set(&mut self, x: usize, y: usize, val: bool) {
-        let wrapped_y = y & (Display::HEIGHT - 1);
-        let wrapped_x = x & (Display::WIDTH - 1);
-        self.pixels[wrapped_y][wrapped_x] = val;
+    /// XOR a sprite byte into row `y` starting at column `x`, returning
+    /// whether any lit pixel was turned off. Pixels past the right edge wrap
+    /// around when `wrap` is set and are dropped otherwise.
+    pub(crate) fn draw_sprite_row(&mut self, x: usize, y: usize, byte: u8, wrap: bool) -> bool {
+        let x = (x & (Display::WIDTH - 1)) as u32;
+        let sprite = (byte as u64) << 56;
+        let bits = if wrap {
+            sprite.rotate_right(x)
+        } else {
+            sprite >> x
+        };
+        self.toggle(y & (Display::HEIGHT - 1), bits)
+    }
+
+    fn toggle(&mut self, y: usize, bits: u64) -> bool {
+        if bits == 0 {
+            return false;
+        }
+        let collision = self.rows[y] & bits != 0;
+        self.rows[y] ^= bits;
+        self.dirty_rows |= 1 << y;
+        self.dirty_columns |= bits;
+        self.stale_rows |= 1 << y;
+        let mut remaining = bits;
+        while remaining != 0 {
+            let x = remaining.leading_zeros() as usize;
+            self.hash ^= pixel_key(x, y);
+            remaining &= !(1 << (63 - x));
+        }
+        collision
     }
 
-    pub(crate) fn get(&self, x: usize, y: usize) -> Result<bool, String> {
+    /// Whether the pixel at (`x`, `y`) is lit, wrapping both coordinates.
+    pub fn get(&self, x: usize, y: usize) -> bool {
         let wrapped_y = y & (Display::HEIGHT - 1);
         let wrapped_x = x & (Display::WIDTH - 1);
-        Ok(self.pixels[wrapped_y][wrapped_x])
+        self.rows[wrapped_y] >> (63 - wrapped_x) & 1 == 1
+    }
+
+    /// The screen as one `u64` per row, the most significant bit being the
+    /// leftmost pixel.
+    pub fn rows(&self) -> &[u64; Display::HEIGHT] {
+        &self.rows
     }
 
     pub(crate) fn clear(&mut self) {
-        self.pixels = [[false; Display::WIDTH]; Display::HEIGHT];
+        for (y, row) in self.rows.iter().enumerate().filter(|(_, row)| **row != 0) {
+            self.dirty_rows |= 1 << y;
+            self.dirty_columns |= row;
+            self.stale_rows |= 1 << y;
+        }
+        self.rows = [0; Display::HEIGHT];
+        self.hash = 0;
+    }
+
+    /// Whether any pixel changed since the last `clear_dirty`.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_rows != 0
+    }
+
+    /// Bit `y` is set if row `y` changed since the last `clear_dirty`.
+    pub fn dirty_rows(&self) -> u32 {
+        self.dirty_rows
     }
 
+    /// Smallest rectangle covering every pixel changed since the last
+    /// `clear_dirty`, or `None` if nothing changed.
+    pub fn dirty_rect(&self) -> Option<Rect> {
+        if !self.is_dirty() {
+            return None;
+        }
+        let span = |first: u32, last: u32| (first as u8, (last - first + 1) as u8);
+        let (y, height) = span(
+            self.dirty_rows.trailing_zeros(),
+            31 - self.dirty_rows.leading_zeros(),
+        );
+        let (x, width) = span(
+            self.dirty_columns.leading_zeros(),
+            63 - self.dirty_columns.trailing_zeros(),
+        );
+        Some(Rect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Start tracking changes afresh, e.g. after presenting a frame.
+    pub fn clear_dirty(&mut self) {
+        self.dirty_rows = 0;
+        self.dirty_columns = 0;
+    }
+
+    /// Hash of the lit pixels, equal for equal frames. Updated as pixels
+    /// change, so reading it costs nothing.
+    pub fn frame_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The screen as one bool per pixel, row by row. Only rows that changed
+    /// since the last call are unpacked.
     pub(crate) fn get_frame_buffer(&mut self) -> &[bool] {
-        self.pixels.as_flattened()
+        while self.stale_rows != 0 {
+            let y = self.stale_rows.trailing_zeros() as usize;
+            let row = self.rows[y];
+            let pixels = &mut self.view[y * Display::WIDTH..(y + 1) * Display::WIDTH];
+            for (x, pixel) in pixels.iter_mut().enumerate() {
+                *pixel = row >> (63 - x) & 1 == 1;
+            }
+            self.stale_rows &= !(1 << y);
+        }
+        &self.view
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save(&self, w: &mut StateWriter) {
+        // 8 pixels per byte, leftmost pixel in the high bit.
+        for row in self.rows {
+            w.bytes(&row.to_be_bytes());
+        }
+    }
+
+    pub(crate) fn load(&mut self, r: &mut StateReader) -> Result<(), VMError> {
+        let packed = r.bytes(Display::WIDTH * Display::HEIGHT / 8)?;
+        self.clear();
+        for (y, chunk) in packed.chunks_exact(8).enumerate() {
+            let mut row = [0; 8];
+            row.copy_from_slice(chunk);
+            self.toggle(y, u64::from_be_bytes(row));
+        }
+        // Everything may have changed.
+        self.dirty_rows = u32::MAX;
+        self.dirty_columns = u64::MAX;
+        Ok(())
     }
 }
 
+/// Pseudo-random key for a pixel (splitmix64 of its index), so frames can be
+/// hashed by XORing the keys of the pixels that are lit.
+fn pixel_key(x: usize, y: usize) -> u64 {
+    let mut z = ((y * Display::WIDTH + x) as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,17 +188,90 @@ mod tests {
     #[test]
     fn test_display() {
         let mut display = Display::new();
-        display.set(1, 2, true);
-        assert_eq!(display.get(1, 2).unwrap(), true);
-        assert_eq!(display.get(1, 3).unwrap(), false);
+        assert!(!display.draw_sprite_row(1, 2, 0x80, false));
+        assert!(display.get(1, 2));
+        assert!(!display.get(1, 3));
+        assert_eq!(display.rows()[2], 1 << 62);
+        display.clear();
+        assert!(!display.get(1, 2));
+    }
+
+    #[test]
+    fn test_draw_sprite_row() {
+        let mut display = Display::new();
+        assert!(!display.draw_sprite_row(60, 0, 0xFF, false));
+        assert_eq!(display.rows()[0], 0xF);
+        assert!(display.draw_sprite_row(62, 0, 0xC0, false));
+        assert_eq!(display.rows()[0], 0xC);
+
+        display.clear();
+        assert!(!display.draw_sprite_row(60, 33, 0xFF, true));
+        assert_eq!(display.rows()[1], 0xF000_0000_0000_000F);
+    }
+
+    #[test]
+    fn test_frame_buffer_view() {
+        let mut display = Display::new();
+        display.draw_sprite_row(3, 1, 0xA0, false);
+        let frame = display.get_frame_buffer();
+        assert!(frame[Display::WIDTH + 3]);
+        assert!(!frame[Display::WIDTH + 4]);
+        assert!(frame[Display::WIDTH + 5]);
+
+        display.clear();
+        assert!(display.get_frame_buffer().iter().all(|p| !p));
+    }
+
+    #[test]
+    fn test_dirty_tracking() {
+        let mut display = Display::new();
+        assert!(!display.is_dirty());
+        assert_eq!(display.dirty_rect(), None);
+
+        display.draw_sprite_row(3, 4, 0x80, false);
+        display.draw_sprite_row(10, 6, 0x80, false);
+        assert_eq!(display.dirty_rows(), 0b101_0000);
+        assert_eq!(
+            display.dirty_rect(),
+            Some(Rect {
+                x: 3,
+                y: 4,
+                width: 8,
+                height: 3
+            })
+        );
+
+        // A blank sprite row isn't a change.
+        display.clear_dirty();
+        display.draw_sprite_row(3, 4, 0, false);
+        assert!(!display.is_dirty());
+
+        display.clear();
+        assert_eq!(display.dirty_rows(), 0b101_0000);
+        display.clear_dirty();
+        display.clear();
+        assert!(!display.is_dirty());
+    }
+
+    #[test]
+    fn test_frame_hash() {
+        let mut display = Display::new();
+        let empty = display.frame_hash();
+        display.draw_sprite_row(1, 2, 0x80, false);
+        let one = display.frame_hash();
+        assert_ne!(one, empty);
+        display.draw_sprite_row(5, 5, 0x80, false);
+        assert_ne!(display.frame_hash(), one);
+        display.draw_sprite_row(5, 5, 0x80, false);
+        assert_eq!(display.frame_hash(), one);
         display.clear();
-        assert_eq!(display.get(1, 2).unwrap(), false);
+        assert_eq!(display.frame_hash(), empty);
     }
 
     #[test]
     fn test_display_wrap() {
         let mut display = Display::new();
-        display.set(123, 45, true);
-        assert_eq!(display.get(59, 13).unwrap(), true);
+        display.draw_sprite_row(123, 45, 0x80, false);
+        assert!(display.get(59, 13));
     }
 }
@@ -0,0 +1,404 @@
+//! Episodic environment wrapper for reinforcement learning, in the style of
+//! Gym's Atari environments.
+//!
+//! Each action presses at most one key and runs `frame_skip` frames. Rewards
+//! and episode ends come from values the ROM keeps in memory, such as a score
+//! or a lives counter, described per ROM with `MemoryValue`.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::vm::{Chip8VM, VMError, DEFAULT_CYCLES_PER_FRAME};
+use alloc::vec::Vec;
+
+/// How a value is stored in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Big-endian unsigned integer.
+    Binary,
+    /// One decimal digit per byte, most significant first, as written by FX33.
+    Bcd,
+}
+
+/// A number read from `len` bytes of memory starting at `addr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryValue {
+    pub addr: u16,
+    pub len: u8,
+    pub encoding: Encoding,
+}
+
+impl MemoryValue {
+    /// Longest `Binary` value that fits in an i64.
+    pub const MAX_BINARY_LEN: u8 = 8;
+    /// Most `Bcd` digits that fit in an i64.
+    pub const MAX_BCD_DIGITS: u8 = 18;
+
+    /// A single byte, the common case for scores and lives.
+    pub fn byte(addr: u16) -> MemoryValue {
+        MemoryValue {
+            addr,
+            len: 1,
+            encoding: Encoding::Binary,
+        }
+    }
+
+    /// A decimal number of up to `MAX_BCD_DIGITS` digits.
+    pub fn bcd(addr: u16, digits: u8) -> Result<MemoryValue, VMError> {
+        let value = MemoryValue {
+            addr,
+            len: digits,
+            encoding: Encoding::Bcd,
+        };
+        value.validate()?;
+        Ok(value)
+    }
+
+    /// Check that the value fits in the i64 `read` returns.
+    pub fn validate(&self) -> Result<(), VMError> {
+        let max = match self.encoding {
+            Encoding::Binary => MemoryValue::MAX_BINARY_LEN,
+            Encoding::Bcd => MemoryValue::MAX_BCD_DIGITS,
+        };
+        if self.len > max {
+            return Err(VMError::InvalidMemoryValue {
+                addr: self.addr,
+                len: self.len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Values that fail `validate` wrap around instead of panicking.
+    pub fn read(&self, memory: &[u8]) -> i64 {
+        (0..self.len as usize)
+            .map(|i| memory[(self.addr as usize + i) % memory.len()] as i64)
+            .fold(0, |acc: i64, b| match self.encoding {
+                Encoding::Binary => acc.wrapping_shl(8) | b,
+                Encoding::Bcd => acc.wrapping_mul(10).wrapping_add(b),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+/// Ends the episode when `value` compared to `target` holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoneCondition {
+    pub value: MemoryValue,
+    pub comparison: Comparison,
+    pub target: i64,
+}
+
+impl DoneCondition {
+    pub fn new(value: MemoryValue, comparison: Comparison, target: i64) -> DoneCondition {
+        DoneCondition {
+            value,
+            comparison,
+            target,
+        }
+    }
+
+    fn is_met(&self, memory: &[u8]) -> bool {
+        let value = self.value.read(memory);
+        match self.comparison {
+            Comparison::Equal => value == self.target,
+            Comparison::NotEqual => value != self.target,
+            Comparison::Less => value < self.target,
+            Comparison::Greater => value > self.target,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvConfig {
+    /// Frames run per action.
+    pub frame_skip: u32,
+    /// Instructions per 60Hz frame.
+    pub cycles_per_frame: u32,
+    /// Keys the agent can press. Action 0 is always "no key", action `n`
+    /// presses `keys[n - 1]`.
+    pub keys: Vec<u8>,
+    /// The reward for a step is how much this value changed.
+    pub reward: Option<MemoryValue>,
+    /// The episode ends when any of these holds.
+    pub done: Vec<DoneCondition>,
+    /// Truncate episodes after this many frames.
+    pub max_frames: Option<u32>,
+    pub seed: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            frame_skip: 4,
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            keys: (0..16).collect(),
+            reward: None,
+            done: Vec::new(),
+            max_frames: None,
+            seed: 0,
+        }
+    }
+}
+
+pub struct Env {
+    rom: Vec<u8>,
+    config: EnvConfig,
+    vm: Chip8VM,
+    rng: StdRng,
+    held_key: Option<u8>,
+    score: i64,
+    frames: u32,
+    done: bool,
+}
+
+impl Env {
+    /// Build an environment for `rom`. Call `reset` before the first `step`.
+    pub fn new(rom: &[u8], config: EnvConfig) -> Result<Env, VMError> {
+        if let Some(&key) = config.keys.iter().find(|&&k| k > 0xF) {
+            return Err(VMError::UnknownKey(key));
+        }
+        let done_values = config.done.iter().map(|c| &c.value);
+        for value in config.reward.iter().chain(done_values) {
+            value.validate()?;
+        }
+        let mut vm = Chip8VM::with_rng(StdRng::seed_from_u64(config.seed));
+        vm.load_rom_bytes(rom)?;
+        Ok(Env {
+            rom: rom.to_vec(),
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            vm,
+            held_key: None,
+            score: 0,
+            frames: 0,
+            done: true,
+        })
+    }
+
+    /// Number of discrete actions, including the no-op.
+    pub fn action_count(&self) -> usize {
+        self.config.keys.len() + 1
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn vm(&self) -> &Chip8VM {
+        &self.vm
+    }
+
+    /// Frames run since the last reset.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Start a new episode and return the first observation. Episodes get
+    /// different CXNN seeds, but the sequence is fixed by `EnvConfig::seed`.
+    pub fn reset(&mut self) -> Result<&[bool], VMError> {
+        let mut vm = Chip8VM::with_rng(StdRng::seed_from_u64(self.rng.gen()));
+        vm.load_rom_bytes(&self.rom)?;
+        self.vm = vm;
+        self.held_key = None;
+        self.frames = 0;
+        self.done = false;
+        self.score = self.read_score();
+        Ok(self.vm.get_frame_buffer())
+    }
+
+    /// Hold the key for `action` for `frame_skip` frames. Returns the new
+    /// observation, the reward earned and whether the episode is over.
+    /// Stepping a finished episode does nothing until `reset` is called.
+    pub fn step(&mut self, action: usize) -> Result<(&[bool], i64, bool), VMError> {
+        if self.done {
+            return Ok((self.vm.get_frame_buffer(), 0, true));
+        }
+        let key = match action {
+            0 => None,
+            n => Some(
+                *self
+                    .config
+                    .keys
+                    .get(n - 1)
+                    .ok_or(VMError::InvalidAction(n))?,
+            ),
+        };
+        if key != self.held_key {
+            if let Some(old) = self.held_key {
+                self.vm.handle_key(old, false);
+            }
+            if let Some(new) = key {
+                self.vm.handle_key(new, true);
+            }
+            self.held_key = key;
+        }
+
+        for _ in 0..self.config.frame_skip {
+            for _ in 0..self.config.cycles_per_frame {
+                self.vm.cycle()?;
+            }
+            self.vm.tick_timers();
+            self.frames += 1;
+            if self.is_done() {
+                self.done = true;
+                break;
+            }
+        }
+
+        let score = self.read_score();
+        let reward = score - self.score;
+        self.score = score;
+        Ok((self.vm.get_frame_buffer(), reward, self.done))
+    }
+
+    fn read_score(&self) -> i64 {
+        self.config
+            .reward
+            .map_or(0, |value| value.read(self.vm.memory()))
+    }
+
+    fn is_done(&self) -> bool {
+        let memory = self.vm.memory();
+        self.config.done.iter().any(|c| c.is_met(memory))
+            || self.config.max_frames.is_some_and(|max| self.frames >= max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // I = 0x300, then loop: V0 += 1, store V0 at I
+    const COUNTER_ROM: [u8; 8] = [0xA3, 0x00, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x02];
+
+    #[test]
+    fn test_memory_value() {
+        let memory = [0x01, 0x02, 0x09, 0x04];
+        assert_eq!(MemoryValue::byte(1).read(&memory), 2);
+        let word = MemoryValue {
+            addr: 0,
+            len: 2,
+            encoding: Encoding::Binary,
+        };
+        assert_eq!(word.read(&memory), 0x0102);
+        assert_eq!(MemoryValue::bcd(1, 3).unwrap().read(&memory), 294);
+    }
+
+    #[test]
+    fn test_memory_value_limits() {
+        let nines = [9; 19];
+        let bcd = MemoryValue::bcd(0, 18).unwrap();
+        assert_eq!(bcd.read(&nines), 999_999_999_999_999_999);
+        assert_eq!(
+            MemoryValue::bcd(0, 19),
+            Err(VMError::InvalidMemoryValue { addr: 0, len: 19 })
+        );
+
+        let mut binary = MemoryValue {
+            addr: 0,
+            len: 8,
+            encoding: Encoding::Binary,
+        };
+        assert_eq!(binary.validate(), Ok(()));
+        assert_eq!(
+            binary.read(&[0x7F, 1, 2, 3, 4, 5, 6, 7]),
+            0x7F01_0203_0405_0607
+        );
+        binary.len = 9;
+        assert_eq!(
+            binary.validate(),
+            Err(VMError::InvalidMemoryValue { addr: 0, len: 9 })
+        );
+
+        // Public fields skip `bcd`, so `Env::new` checks every value too.
+        let long_bcd = MemoryValue { len: 19, ..bcd };
+        let config = EnvConfig {
+            reward: Some(long_bcd),
+            ..EnvConfig::default()
+        };
+        assert!(Env::new(&COUNTER_ROM, config).is_err());
+        let config = EnvConfig {
+            done: vec![DoneCondition::new(binary, Comparison::Equal, 0)],
+            ..EnvConfig::default()
+        };
+        assert_eq!(
+            Env::new(&COUNTER_ROM, config).err(),
+            Some(VMError::InvalidMemoryValue { addr: 0, len: 9 })
+        );
+    }
+
+    #[test]
+    fn test_reward_and_done() {
+        let config = EnvConfig {
+            frame_skip: 2,
+            cycles_per_frame: 3,
+            reward: Some(MemoryValue::byte(0x300)),
+            done: vec![DoneCondition::new(
+                MemoryValue::byte(0x300),
+                Comparison::Greater,
+                9,
+            )],
+            ..EnvConfig::default()
+        };
+        let mut env = Env::new(&COUNTER_ROM, config).unwrap();
+        assert_eq!(env.action_count(), 17);
+        env.reset().unwrap();
+
+        // First step includes the SetIndex, after that one store per frame.
+        let (_, reward, done) = env.step(0).unwrap();
+        assert_eq!((reward, done), (2, false));
+        let mut total = reward;
+        while let (_, reward, false) = env.step(1).unwrap() {
+            total += reward;
+        }
+        assert!(env.vm().memory()[0x300] > 9);
+        assert!(env.step(0).unwrap().2);
+        assert!(env.step(17).is_ok());
+
+        assert!(total > 0);
+
+        env.reset().unwrap();
+        assert_eq!(env.frames(), 0);
+        assert_eq!(env.step(17), Err(VMError::InvalidAction(17)));
+        assert_eq!(env.step(256), Err(VMError::InvalidAction(256)));
+    }
+
+    #[test]
+    fn test_deterministic_with_seed() {
+        // loop: V0 = random, I = 0x300, store V0
+        let rom = [0xC0, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00];
+        let config = EnvConfig {
+            reward: Some(MemoryValue::byte(0x300)),
+            max_frames: Some(40),
+            seed: 7,
+            ..EnvConfig::default()
+        };
+        let run = |config: EnvConfig| {
+            let mut env = Env::new(&rom, config).unwrap();
+            let mut rewards = Vec::new();
+            for _ in 0..2 {
+                env.reset().unwrap();
+                let mut done = false;
+                while !done {
+                    let (_, reward, d) = env.step(0).unwrap();
+                    rewards.push(reward);
+                    done = d;
+                }
+            }
+            rewards
+        };
+        let rewards = run(config.clone());
+        assert_eq!(rewards.len(), 20);
+        assert_eq!(rewards, run(config.clone()));
+        assert_ne!(rewards[..10], rewards[10..]);
+        assert_ne!(rewards, run(EnvConfig { seed: 8, ..config }));
+    }
+}
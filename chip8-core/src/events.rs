@@ -0,0 +1,117 @@
+use crate::vm::VMError;
+
+/// Events kept before the oldest are dropped.
+pub const EVENT_CAPACITY: usize = 128;
+
+/// Area of the screen a draw touched, in display pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+/// Something the VM did that frontends and tools may want to react to,
+/// instead of polling its state every frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    DisplayCleared,
+    /// A sprite was drawn. Sprites that wrap around an edge report the full
+    /// width or height of the screen.
+    DisplayDrawn(Rect),
+    /// The sound timer went from 0 to running.
+    SoundStarted,
+    /// The sound timer ran out or was set to 0.
+    SoundStopped,
+    /// FX0A is waiting for a key to store in `register`.
+    KeyWaitBegan {
+        register: u8,
+    },
+    /// The wait ended when `key` was released.
+    KeyWaitEnded {
+        key: u8,
+    },
+    SubroutineCalled {
+        addr: u16,
+        return_addr: u16,
+    },
+    /// Returned from a subroutine to `addr`.
+    SubroutineReturned {
+        addr: u16,
+    },
+    /// The program jumped to itself at `pc`, which is how most ROMs stop.
+    /// Reported once per spin loop, again after the VM runs anything else.
+    Halted {
+        pc: u16,
+    },
+    /// `cycle` returned this error.
+    Fault(VMError),
+}
+
+/// Fixed size ring buffer of events, so recording never allocates.
+pub(crate) struct EventQueue {
+    events: [Event; EVENT_CAPACITY],
+    head: usize,
+    len: usize,
+    dropped: usize,
+}
+
+impl EventQueue {
+    pub(crate) fn new() -> EventQueue {
+        EventQueue {
+            events: [const { Event::DisplayCleared }; EVENT_CAPACITY],
+            head: 0,
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Record an event, dropping the oldest one when full.
+    pub(crate) fn push(&mut self, event: Event) {
+        if self.len == EVENT_CAPACITY {
+            self.head = (self.head + 1) % EVENT_CAPACITY;
+            self.len -= 1;
+            self.dropped += 1;
+        }
+        self.events[(self.head + self.len) % EVENT_CAPACITY] = event;
+        self.len += 1;
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = core::mem::replace(&mut self.events[self.head], Event::DisplayCleared);
+        self.head = (self.head + 1) % EVENT_CAPACITY;
+        self.len -= 1;
+        Some(event)
+    }
+
+    /// Events lost to overflow since the last call.
+    pub(crate) fn take_dropped(&mut self) -> usize {
+        core::mem::take(&mut self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let mut queue = EventQueue::new();
+        queue.push(Event::SoundStarted);
+        queue.push(Event::SoundStopped);
+        assert_eq!(queue.pop(), Some(Event::SoundStarted));
+        assert_eq!(queue.pop(), Some(Event::SoundStopped));
+        assert_eq!(queue.pop(), None);
+
+        for pc in 0..EVENT_CAPACITY as u16 + 2 {
+            queue.push(Event::Halted { pc });
+        }
+        assert_eq!(queue.take_dropped(), 2);
+        assert_eq!(queue.take_dropped(), 0);
+        assert_eq!(queue.pop(), Some(Event::Halted { pc: 2 }));
+    }
+}
@@ -0,0 +1,13 @@
+/// What the VM does when a program reaches past the end of memory, e.g. FX55
+/// with I near 0xFFF or PC running off the end of RAM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FaultPolicy {
+    /// Addresses wrap to 12 bits, like the address bus of the original
+    /// interpreters.
+    #[default]
+    Wrap,
+    /// Stop with `VMError::MemoryOutOfBounds`.
+    Fault,
+    /// Reads return 0 and writes are dropped. PC still wraps.
+    Ignore,
+}
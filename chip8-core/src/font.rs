@@ -0,0 +1,153 @@
+/// Sprites for the hex digits 0-F that FX29 points I at, 5 rows of 4 pixels
+/// each. Interpreters drew them differently, so each platform has its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    pub glyphs: [[u8; Font::GLYPH_HEIGHT]; 16],
+}
+
+impl Font {
+    pub const GLYPH_HEIGHT: usize = 5;
+    /// Bytes the font takes up in memory.
+    pub const SIZE: usize = 16 * Font::GLYPH_HEIGHT;
+
+    /// The COSMAC VIP interpreter's font.
+    pub const VIP: Font = Font::from_bytes([
+        0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+        0x60, 0x20, 0x20, 0x20, 0x70, // 1
+        0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+        0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+        0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+        0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+        0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+        0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+        0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+        0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+        0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+        0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+        0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+        0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+        0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+        0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+    ]);
+
+    /// CHIP-48's font, which most modern interpreters copied.
+    pub const CHIP48: Font = Font::from_bytes([
+        0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+        0x20, 0x60, 0x20, 0x20, 0x70, // 1
+        0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+        0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+        0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+        0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+        0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+        0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+        0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+        0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+        0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+        0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+        0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+        0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+        0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+        0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+    ]);
+
+    /// SUPER-CHIP kept the CHIP-48 small font, its large font is only used in
+    /// hi-res mode.
+    pub const SUPER_CHIP: Font = Font::CHIP48;
+
+    /// The DREAM 6800's narrow 3-pixel font.
+    pub const DREAM6800: Font = Font::from_bytes([
+        0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+        0x40, 0x40, 0x40, 0x40, 0x40, // 1
+        0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+        0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+        0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+        0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+        0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+        0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+        0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+        0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+        0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+        0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+        0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+        0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+        0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+        0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+    ]);
+
+    /// The ETI-660's narrow 3-pixel font.
+    pub const ETI660: Font = Font::from_bytes([
+        0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+        0x20, 0x20, 0x20, 0x20, 0x20, // 1
+        0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+        0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+        0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+        0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+        0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+        0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+        0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+        0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+        0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+        0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+        0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+        0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+        0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+        0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+    ]);
+
+    const NAMED: [(&'static str, Font); 5] = [
+        ("vip", Font::VIP),
+        ("chip48", Font::CHIP48),
+        ("schip", Font::SUPER_CHIP),
+        ("dream6800", Font::DREAM6800),
+        ("eti660", Font::ETI660),
+    ];
+
+    /// Build a font from 80 bytes laid out the way it sits in memory, 5 bytes
+    /// per digit from 0 to F.
+    pub const fn from_bytes(bytes: [u8; Font::SIZE]) -> Font {
+        let mut glyphs = [[0; Font::GLYPH_HEIGHT]; 16];
+        let mut i = 0;
+        while i < Font::SIZE {
+            glyphs[i / Font::GLYPH_HEIGHT][i % Font::GLYPH_HEIGHT] = bytes[i];
+            i += 1;
+        }
+        Font { glyphs }
+    }
+
+    /// Look up a built-in font by name, ignoring case.
+    pub fn named(name: &str) -> Option<Font> {
+        Font::NAMED
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, f)| *f)
+    }
+
+    /// Names accepted by `Font::named`.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Font::NAMED.iter().map(|(n, _)| *n)
+    }
+
+    /// The font as it's laid out in memory.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.glyphs.iter().flatten().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Font::CHIP48.glyphs[1], [0x20, 0x60, 0x20, 0x20, 0x70]);
+        assert_eq!(Font::VIP.bytes().nth(5), Some(0x60));
+        assert_eq!(Font::VIP.bytes().count(), Font::SIZE);
+    }
+
+    #[test]
+    fn test_named() {
+        assert_eq!(Font::named("ETI660"), Some(Font::ETI660));
+        assert_eq!(Font::named("xo-chip"), None);
+        assert!(Font::names().all(|n| Font::named(n).is_some()));
+    }
+}
@@ -1,9 +1,11 @@
+use core::fmt;
+
 type Addr = u16;
 type Vx = u8;
 type Vy = u8;
 type NN = u8;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     Unknown(u16),
     ClearScreen,             // 00E0
@@ -99,6 +101,50 @@ impl Instruction {
     }
 }
 
+// Mnemonics follow Cowgod's Chip-8 technical reference.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+        match *self {
+            Unknown(code) => write!(f, "DW   {:#06X}", code),
+            ClearScreen => write!(f, "CLS"),
+            ExitSubroutine => write!(f, "RET"),
+            Jump(addr) => write!(f, "JP   {:#05X}", addr),
+            CallSubroutine(addr) => write!(f, "CALL {:#05X}", addr),
+            SkipValEqual(vx, nn) => write!(f, "SE   V{:X}, {:#04X}", vx, nn),
+            SkipValNotEqual(vx, nn) => write!(f, "SNE  V{:X}, {:#04X}", vx, nn),
+            SkipRegEqual(vx, vy) => write!(f, "SE   V{:X}, V{:X}", vx, vy),
+            SetVal(vx, nn) => write!(f, "LD   V{:X}, {:#04X}", vx, nn),
+            AddVal(vx, nn) => write!(f, "ADD  V{:X}, {:#04X}", vx, nn),
+            SetReg(vx, vy) => write!(f, "LD   V{:X}, V{:X}", vx, vy),
+            OR(vx, vy) => write!(f, "OR   V{:X}, V{:X}", vx, vy),
+            AND(vx, vy) => write!(f, "AND  V{:X}, V{:X}", vx, vy),
+            XOR(vx, vy) => write!(f, "XOR  V{:X}, V{:X}", vx, vy),
+            Add(vx, vy) => write!(f, "ADD  V{:X}, V{:X}", vx, vy),
+            SubLeft(vx, vy) => write!(f, "SUB  V{:X}, V{:X}", vx, vy),
+            ShiftRight(vx, vy) => write!(f, "SHR  V{:X}, V{:X}", vx, vy),
+            SubRight(vx, vy) => write!(f, "SUBN V{:X}, V{:X}", vx, vy),
+            ShiftLeft(vx, vy) => write!(f, "SHL  V{:X}, V{:X}", vx, vy),
+            SkipRegNotEqual(vx, vy) => write!(f, "SNE  V{:X}, V{:X}", vx, vy),
+            SetIndex(addr) => write!(f, "LD   I, {:#05X}", addr),
+            JumpOffset(addr) => write!(f, "JP   V0, {:#05X}", addr),
+            Random(vx, nn) => write!(f, "RND  V{:X}, {:#04X}", vx, nn),
+            Display(vx, vy, n) => write!(f, "DRW  V{:X}, V{:X}, {}", vx, vy, n),
+            SkipIfPressed(vx) => write!(f, "SKP  V{:X}", vx),
+            SkipNotPressed(vx) => write!(f, "SKNP V{:X}", vx),
+            GetDelayTimer(vx) => write!(f, "LD   V{:X}, DT", vx),
+            SetDelayTimer(vx) => write!(f, "LD   DT, V{:X}", vx),
+            SetSoundTimer(vx) => write!(f, "LD   ST, V{:X}", vx),
+            AddToIndex(vx) => write!(f, "ADD  I, V{:X}", vx),
+            GetKey(vx) => write!(f, "LD   V{:X}, K", vx),
+            FontChar(vx) => write!(f, "LD   F, V{:X}", vx),
+            BinDecConv(vx) => write!(f, "LD   B, V{:X}", vx),
+            StoreMem(vx) => write!(f, "LD   [I], V{:X}", vx),
+            LoadMem(vx) => write!(f, "LD   V{:X}, [I]", vx),
+        }
+    }
+}
+
 fn d_val(instr: u16) -> u8 {
     (instr & 0x00FF) as u8
 }
@@ -111,6 +157,16 @@ fn d_reg2(instr: u16) -> u8 {
     ((instr & 0x00F0) >> 4) as u8
 }
 
+#[test]
+fn test_display_mnemonics() {
+    assert_eq!(Instruction::decode(0x00E0).to_string(), "CLS");
+    assert_eq!(Instruction::decode(0x1EAF).to_string(), "JP   0xEAF");
+    assert_eq!(Instruction::decode(0x62F4).to_string(), "LD   V2, 0xF4");
+    assert_eq!(Instruction::decode(0xD125).to_string(), "DRW  V1, V2, 5");
+    assert_eq!(Instruction::decode(0xFA65).to_string(), "LD   VA, [I]");
+    assert_eq!(Instruction::decode(0xFFFF).to_string(), "DW   0xFFFF");
+}
+
 macro_rules! decode_tests {
     ( $($label:ident : $inp:expr, $pat:pat,)* ) => {
     $(
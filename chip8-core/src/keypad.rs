@@ -1,5 +1,8 @@
+use crate::state::StateReader;
+#[cfg(feature = "alloc")]
+use crate::state::StateWriter;
 use crate::vm::VMError;
-use std::ops::{Index, IndexMut};
+use core::ops::{Index, IndexMut};
 
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -93,7 +96,7 @@ impl Keypad {
         }
     }
 
-    pub(crate) fn is_waiting(&mut self) -> bool {
+    pub(crate) fn is_waiting(&self) -> bool {
         match self.wait_state {
             KeyWait::NotWaiting => false,
             KeyWait::WaitingForPress(_) => true,
@@ -108,6 +111,41 @@ impl Keypad {
     pub(crate) fn set_wait(&mut self, wait_state: KeyWait) {
         self.wait_state = wait_state
     }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save(&self, w: &mut StateWriter) {
+        for state in self.state {
+            w.u8((state == KeyState::Pressed) as u8);
+        }
+        let (tag, val) = match self.wait_state {
+            KeyWait::NotWaiting => (0, 0),
+            KeyWait::WaitingForPress(vx) => (1, vx),
+            KeyWait::WaitingForRelease(key) => (2, key),
+        };
+        w.u8(tag);
+        w.u8(val);
+    }
+
+    pub(crate) fn load(&mut self, r: &mut StateReader) -> Result<(), VMError> {
+        for state in self.state.iter_mut() {
+            *state = match r.u8()? {
+                0 => KeyState::NotPressed,
+                1 => KeyState::Pressed,
+                _ => return Err(VMError::InvalidSaveState()),
+            };
+        }
+        let (tag, val) = (r.u8()?, r.u8()?);
+        if val > 0xF {
+            return Err(VMError::InvalidSaveState());
+        }
+        self.wait_state = match tag {
+            0 => KeyWait::NotWaiting,
+            1 => KeyWait::WaitingForPress(val),
+            2 => KeyWait::WaitingForRelease(val),
+            _ => return Err(VMError::InvalidSaveState()),
+        };
+        Ok(())
+    }
 }
 
 impl Index<Key> for Keypad {
@@ -1,5 +1,27 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "cart")]
+pub mod cart;
 pub mod display;
+#[cfg(feature = "alloc")]
+pub mod env;
+pub mod events;
+pub mod fault;
+pub mod font;
 pub mod instructions;
 pub mod keypad;
 pub mod memory;
+#[cfg(feature = "std")]
+pub mod octo;
+pub mod palette;
+pub mod persistence;
+pub mod platform;
+pub mod quirks;
+#[cfg(feature = "alloc")]
+pub mod render;
+pub mod rom;
+mod state;
 pub mod vm;
@@ -1,25 +1,11 @@
+use core::ops::Deref;
+
+use crate::state::StateReader;
+#[cfg(feature = "alloc")]
+use crate::state::StateWriter;
 use crate::vm::VMError;
 
-const FONT: [[u8; 5]; 16] = [
-    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
-    [0x20, 0x60, 0x20, 0x20, 0x70], // 1
-    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
-    [0xF0, 0x10, 0xF0, 0x10, 0xF0], // 3
-    [0x90, 0x90, 0xF0, 0x10, 0x10], // 4
-    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
-    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
-    [0xF0, 0x10, 0x20, 0x40, 0x40], // 7
-    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
-    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
-    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
-    [0xE0, 0x90, 0xE0, 0x90, 0xE0], // B
-    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
-    [0xE0, 0x90, 0x90, 0x90, 0xE0], // D
-    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
-    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
-];
-
-const RAM_SIZE: usize = 4 * 1024;
+pub(crate) const RAM_SIZE: usize = 4 * 1024;
 
 pub(crate) struct Memory {
     data: [u8; RAM_SIZE],
@@ -27,67 +13,163 @@ pub(crate) struct Memory {
 
 impl Memory {
     pub(crate) fn new() -> Memory {
-        let mut m = Memory {
+        Memory {
             data: [0; RAM_SIZE],
-        };
-        for (i, row) in FONT.iter().enumerate() {
-            for (j, col) in row.iter().enumerate() {
-                m.data[(i * row.len()) * j] = *col
-            }
         }
-        m
     }
 
     pub(crate) fn write(&mut self, addr: usize, val: u8) {
         self.data[addr] = val;
     }
 
-    pub(crate) fn read(&mut self, addr: usize) -> u8 {
+    pub(crate) fn read(&self, addr: usize) -> u8 {
         self.data[addr]
     }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save(&self, w: &mut StateWriter) {
+        w.bytes(&self.data);
+    }
+
+    pub(crate) fn load(&mut self, r: &mut StateReader) -> Result<(), VMError> {
+        self.data.copy_from_slice(r.bytes(RAM_SIZE)?);
+        Ok(())
+    }
 }
 
-static MAX_STACK_SIZE: usize = 100;
+/// Most return addresses any stack can hold, whatever depth is configured.
+pub const MAX_STACK_DEPTH: usize = 64;
 
-pub struct Stack {
-    data: Vec<u16>,
-    sp: usize,
-    max_size: usize,
+/// Where the VIP interpreter kept its stack in a 4K machine.
+pub const VIP_STACK_ADDRESS: u16 = 0xEA0;
+
+/// Where return addresses are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StackMode {
+    /// Inside the interpreter, out of reach of programs.
+    #[default]
+    Internal,
+    /// In RAM from `address` upwards, two big-endian bytes per entry, like the
+    /// VIP at `VIP_STACK_ADDRESS`. Programs that write there corrupt it, just
+    /// as they did on the real machine.
+    Memory { address: u16 },
 }
 
-impl Stack {
-    pub(crate) fn default() -> Stack {
-        Stack::new(MAX_STACK_SIZE)
+/// Return addresses of active subroutine calls, oldest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallStack {
+    frames: [u16; MAX_STACK_DEPTH],
+    len: usize,
+}
+
+impl Deref for CallStack {
+    type Target = [u16];
+
+    fn deref(&self) -> &[u16] {
+        &self.frames[..self.len]
     }
+}
+
+pub(crate) struct Stack {
+    data: [u16; MAX_STACK_DEPTH],
+    sp: usize,
+    max_depth: usize,
+    mode: StackMode,
+}
 
-    pub(crate) fn new(max_size: usize) -> Stack {
+impl Stack {
+    pub(crate) fn new(max_depth: usize, mode: StackMode) -> Stack {
         Stack {
-            data: Vec::with_capacity(max_size),
+            data: [0; MAX_STACK_DEPTH],
             sp: 0,
-            max_size,
+            max_depth: max_depth.min(MAX_STACK_DEPTH),
+            mode,
         }
     }
 
-    pub(crate) fn push(&mut self, value: u16) -> Result<(), VMError> {
-        if self.sp >= self.max_size {
+    pub(crate) fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub(crate) fn mode(&self) -> StackMode {
+        self.mode
+    }
+
+    pub(crate) fn push(&mut self, memory: &mut Memory, value: u16) -> Result<(), VMError> {
+        if self.sp >= self.max_depth {
             return Err(VMError::StackOverflow());
         }
-        if self.sp == self.data.len() {
-            self.data.push(value);
-        } else {
-            self.data[self.sp] = value;
+        match self.mode {
+            StackMode::Internal => self.data[self.sp] = value,
+            StackMode::Memory { address } => {
+                let addr = entry_address(address, self.sp);
+                let [hi, lo] = value.to_be_bytes();
+                memory.write(addr, hi);
+                memory.write((addr + 1) & 0xFFF, lo);
+            }
         }
         self.sp += 1;
         Ok(())
     }
 
-    pub(crate) fn pop(&mut self) -> Result<u16, VMError> {
+    pub(crate) fn pop(&mut self, memory: &Memory) -> Result<u16, VMError> {
         if self.sp == 0 {
             return Err(VMError::StackUnderflow());
         }
         self.sp -= 1;
-        Ok(self.data[self.sp])
+        Ok(self.entry(memory, self.sp))
     }
+
+    fn entry(&self, memory: &Memory, i: usize) -> u16 {
+        match self.mode {
+            StackMode::Internal => self.data[i],
+            StackMode::Memory { address } => {
+                let addr = entry_address(address, i);
+                u16::from_be_bytes([memory.read(addr), memory.read((addr + 1) & 0xFFF)])
+            }
+        }
+    }
+
+    pub(crate) fn call_stack(&self, memory: &Memory) -> CallStack {
+        let mut frames = [0; MAX_STACK_DEPTH];
+        for (i, frame) in frames[..self.sp].iter_mut().enumerate() {
+            *frame = self.entry(memory, i);
+        }
+        CallStack {
+            frames,
+            len: self.sp,
+        }
+    }
+
+    /// Saved after memory, so in-memory stacks only need their depth back.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn save(&self, w: &mut StateWriter, memory: &Memory) {
+        let call_stack = self.call_stack(memory);
+        w.u16(call_stack.len() as u16);
+        for addr in call_stack.iter() {
+            w.u16(*addr);
+        }
+    }
+
+    pub(crate) fn load(&mut self, r: &mut StateReader) -> Result<(), VMError> {
+        let sp = r.u16()? as usize;
+        if sp > self.max_depth {
+            return Err(VMError::InvalidSaveState());
+        }
+        for entry in self.data[..sp].iter_mut() {
+            *entry = r.u16()?;
+        }
+        self.sp = sp;
+        Ok(())
+    }
+}
+
+fn entry_address(base: u16, i: usize) -> usize {
+    (base as usize + 2 * i) & 0xFFF
 }
 
 #[cfg(test)]
@@ -104,10 +186,29 @@ mod tests {
 
     #[test]
     fn test_stack() {
-        let mut stack = Stack::new(MAX_STACK_SIZE);
-        assert!(stack.push(1).is_ok());
-        let result = stack.pop();
+        let mut memory = Memory::new();
+        let mut stack = Stack::new(2, StackMode::Internal);
+        assert!(stack.push(&mut memory, 1).is_ok());
+        let result = stack.pop(&memory);
         assert!(result.is_ok());
-        assert_eq!(stack.pop().unwrap(), 1);
+        assert_eq!(result.unwrap(), 1);
+        assert!(stack.pop(&memory).is_err());
+        assert!(stack.push(&mut memory, 1).is_ok());
+        assert!(stack.push(&mut memory, 2).is_ok());
+        assert!(stack.push(&mut memory, 3).is_err());
+    }
+
+    #[test]
+    fn test_stack_in_memory() {
+        let mut memory = Memory::new();
+        let mut stack = Stack::new(12, StackMode::Memory { address: 0xEA0 });
+        stack.push(&mut memory, 0x234).unwrap();
+        stack.push(&mut memory, 0x456).unwrap();
+        assert_eq!(memory.as_slice()[0xEA0..0xEA4], [0x02, 0x34, 0x04, 0x56]);
+        assert_eq!(*stack.call_stack(&memory), [0x234, 0x456]);
+
+        // Programs can overwrite their return addresses.
+        memory.write(0xEA3, 0x78);
+        assert_eq!(stack.pop(&memory).unwrap(), 0x478);
     }
 }
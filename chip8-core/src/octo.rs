@@ -0,0 +1,1367 @@
+//! Assembler for Octo, the CHIP-8 assembly language stored in Octo carts.
+//!
+//! Covers the instruction syntax (`v0 := 5`, `i := label`, `sprite v0 v1 5`,
+//! ...), `if`/`then`, `if`/`begin`/`else`/`end`, `loop`/`while`/`again`, the
+//! `<`, `>`, `<=` and `>=` pseudo-ops, labels with forward references, and
+//! the `:alias`, `:const`, `:calc`, `:byte`, `:pointer`, `:org`, `:next`,
+//! `:unpack`, `:call`, `:macro`, `:stringmode` and `:assert` directives.
+//! SUPER-CHIP and XO-CHIP instructions are assembled too, whether or not the
+//! VM runs them.
+//!
+//! Like Octo, the program starts with a jump to `main`, left out when `main`
+//! is the first thing in the program.
+
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Where programs are loaded, and the address of the first byte assembled.
+const START: usize = 0x200;
+const MEMORY_SIZE: usize = 0x10000;
+// Macros expanding into more macros forever would otherwise never finish.
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {message}")]
+pub struct AssembleError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Assemble Octo source into a ROM image to load at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let mut asm = Assembler::new(tokenize(source)?);
+    // Jump to main, patched or dropped once main is known.
+    asm.inst(0x00, 0x00)?;
+    while let Some(token) = asm.next() {
+        asm.statement(token)?;
+    }
+    asm.finish()
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+    // A quoted string, which never names anything.
+    quoted: bool,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, AssembleError> {
+    let mut tokens = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '#' {
+                break;
+            } else if c == '"' {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some('r') => string.push('\r'),
+                            Some('0') => string.push('\0'),
+                            Some(c) => string.push(c),
+                            None => return Err(error(line, "Unterminated string")),
+                        },
+                        Some(c) => string.push(c),
+                        None => return Err(error(line, "Unterminated string")),
+                    }
+                }
+                tokens.push(Token {
+                    text: string,
+                    line,
+                    quoted: true,
+                });
+            } else {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token {
+                    text: word,
+                    line,
+                    quoted: false,
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn error(line: usize, message: impl ToString) -> AssembleError {
+    AssembleError {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// A number, or a label that isn't defined yet.
+enum Value {
+    Known(i64),
+    Forward(String),
+}
+
+#[derive(Clone, Copy)]
+enum PatchKind {
+    // The low 12 bits of an instruction.
+    Wide,
+    // A 16-bit word.
+    Long,
+    // The two `:unpack` loads: the address's high nybble then low byte.
+    Unpack,
+    // `:unpack long`: the address's high byte then low byte.
+    UnpackLong,
+}
+
+struct Patch {
+    addr: usize,
+    name: String,
+    line: usize,
+    kind: PatchKind,
+}
+
+struct Loop {
+    start: usize,
+    line: usize,
+    // `while` jumps out, patched by `again`.
+    exits: Vec<usize>,
+}
+
+struct Branch {
+    // Jump patched by `else` or `end`.
+    jump: usize,
+    line: usize,
+    has_else: bool,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+    calls: usize,
+}
+
+struct StringMode {
+    alphabet: Vec<char>,
+    body: Vec<Token>,
+}
+
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+struct Condition {
+    reg: u8,
+    op: String,
+    operand: Option<Token>,
+}
+
+struct Assembler {
+    tokens: Vec<Token>,
+    pos: usize,
+    // Line of the last token read, for errors.
+    line: usize,
+    memory: Vec<u8>,
+    here: usize,
+    // One past the highest address written.
+    end: usize,
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    patches: Vec<Patch>,
+    loops: Vec<Loop>,
+    branches: Vec<Branch>,
+    macros: HashMap<String, Macro>,
+    string_modes: HashMap<String, Vec<StringMode>>,
+    expansions: usize,
+    jump_to_main: bool,
+}
+
+impl Assembler {
+    fn new(tokens: Vec<Token>) -> Assembler {
+        Assembler {
+            tokens,
+            pos: 0,
+            line: 1,
+            memory: vec![0; MEMORY_SIZE],
+            here: START,
+            end: START,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            patches: Vec::new(),
+            loops: Vec::new(),
+            branches: Vec::new(),
+            macros: HashMap::new(),
+            string_modes: HashMap::new(),
+            expansions: 0,
+            jump_to_main: true,
+        }
+    }
+
+    fn error(&self, message: impl ToString) -> AssembleError {
+        error(self.line, message)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        self.line = token.line;
+        Some(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.text.as_str())
+    }
+
+    fn expect_token(&mut self) -> Result<Token, AssembleError> {
+        self.next()
+            .ok_or_else(|| self.error("Unexpected end of program"))
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AssembleError> {
+        let token = self.expect_token()?;
+        if token.text != text || token.quoted {
+            return Err(self.error(format!("Expected '{}', got '{}'", text, token.text)));
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self, b: u8) -> Result<(), AssembleError> {
+        if self.here >= MEMORY_SIZE {
+            return Err(self.error("Program doesn't fit in 64K"));
+        }
+        self.memory[self.here] = b;
+        self.here += 1;
+        self.end = self.end.max(self.here);
+        Ok(())
+    }
+
+    fn inst(&mut self, a: u8, b: u8) -> Result<(), AssembleError> {
+        self.byte(a)?;
+        self.byte(b)
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AssembleError> {
+        if token.quoted {
+            return Err(self.error(format!("Unexpected string \"{}\"", token.text)));
+        }
+        let t = token.text.as_str();
+        match t {
+            ":" => {
+                let name = self.name()?;
+                if name == "main" && self.here == START + 2 && self.jump_to_main {
+                    // Nothing but the jump so far, so main can start at 0x200.
+                    self.here = START;
+                    self.end = START;
+                    self.jump_to_main = false;
+                }
+                self.define(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define(name, self.here + 1)?;
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let reg = self.register()?;
+                self.aliases.insert(name, reg);
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = match self.value()? {
+                    Value::Known(n) => n as f64,
+                    Value::Forward(label) => {
+                        return Err(self.error(format!("Undefined name '{}'", label)))
+                    }
+                };
+                self.define_constant(name, value)?;
+            }
+            ":calc" => {
+                let name = self.name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.define_constant(name, value)?;
+            }
+            ":byte" => {
+                let b = self.byte_value()?;
+                self.byte(b)?;
+            }
+            ":pointer" => self.long_word()?,
+            ":org" => {
+                let addr = self.known_value()?;
+                if !(0..MEMORY_SIZE as i64).contains(&addr) {
+                    return Err(self.error(format!("Address {} is outside memory", addr)));
+                }
+                self.here = addr as usize;
+            }
+            ":unpack" => {
+                let (hi, lo) = (self.alias("unpack-hi", 0x0), self.alias("unpack-lo", 0x1));
+                let at = self.here;
+                if self.peek() == Some("long") {
+                    self.next();
+                    let addr = self.value()?;
+                    self.inst(0x60 | hi, 0)?;
+                    self.inst(0x60 | lo, 0)?;
+                    self.resolve(at, addr, PatchKind::UnpackLong)?;
+                } else {
+                    let nybble = self.tiny_value()?;
+                    let addr = self.value()?;
+                    self.inst(0x60 | hi, nybble << 4)?;
+                    self.inst(0x60 | lo, 0)?;
+                    self.resolve(at, addr, PatchKind::Unpack)?;
+                }
+            }
+            ":call" => self.wide_inst(0x2)?,
+            ":breakpoint" | ":proto" => {
+                self.expect_token()?;
+            }
+            ":monitor" => {
+                self.expect_token()?;
+                self.expect_token()?;
+            }
+            ":assert" => {
+                let message = match self.tokens.get(self.pos) {
+                    Some(token) if token.quoted => self.next().map(|t| t.text),
+                    _ => None,
+                };
+                self.expect("{")?;
+                if self.calc()? == 0.0 {
+                    return Err(self.error(match message {
+                        Some(message) => format!("Assertion failed: {}", message),
+                        None => "Assertion failed".to_string(),
+                    }));
+                }
+            }
+            ":macro" => self.define_macro()?,
+            ":stringmode" => self.define_string_mode()?,
+            "return" | ";" => self.inst(0x00, 0xEE)?,
+            "clear" => self.inst(0x00, 0xE0)?,
+            "hires" => self.inst(0x00, 0xFF)?,
+            "lores" => self.inst(0x00, 0xFE)?,
+            "exit" => self.inst(0x00, 0xFD)?,
+            "scroll-left" => self.inst(0x00, 0xFC)?,
+            "scroll-right" => self.inst(0x00, 0xFB)?,
+            "scroll-down" => {
+                let n = self.tiny_value()?;
+                self.inst(0x00, 0xC0 | n)?;
+            }
+            "scroll-up" => {
+                let n = self.tiny_value()?;
+                self.inst(0x00, 0xD0 | n)?;
+            }
+            "audio" => self.inst(0xF0, 0x02)?,
+            "plane" => {
+                let n = self.tiny_value()?;
+                self.inst(0xF0 | n, 0x01)?;
+            }
+            "bcd" => self.reg_inst(0x33)?,
+            "saveflags" => self.reg_inst(0x75)?,
+            "loadflags" => self.reg_inst(0x85)?,
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek() == Some("-") {
+                    self.next();
+                    let y = self.register()?;
+                    let n = if t == "save" { 0x2 } else { 0x3 };
+                    self.inst(0x50 | x, (y << 4) | n)?;
+                } else {
+                    let n = if t == "save" { 0x55 } else { 0x65 };
+                    self.inst(0xF0 | x, n)?;
+                }
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.tiny_value()?;
+                self.inst(0xD0 | x, (y << 4) | n)?;
+            }
+            "jump" => self.wide_inst(0x1)?,
+            "jump0" => self.wide_inst(0xB)?,
+            "native" => self.wide_inst(0x0)?,
+            "i" => match self.expect_token()?.text.as_str() {
+                ":=" => match self.peek() {
+                    Some("hex") => {
+                        self.next();
+                        self.reg_inst(0x29)?;
+                    }
+                    Some("bighex") => {
+                        self.next();
+                        self.reg_inst(0x30)?;
+                    }
+                    Some("long") => {
+                        self.next();
+                        self.inst(0xF0, 0x00)?;
+                        self.long_word()?;
+                    }
+                    _ => self.wide_inst(0xA)?,
+                },
+                "+=" => self.reg_inst(0x1E)?,
+                op => return Err(self.error(format!("Unknown operator 'i {}'", op))),
+            },
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let n = match t {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.reg_inst(n)?;
+            }
+            "if" => {
+                let condition = self.condition()?;
+                match self.expect_token()?.text.as_str() {
+                    "then" => self.conditional(condition, false)?,
+                    "begin" => {
+                        self.conditional(condition, true)?;
+                        self.branches.push(Branch {
+                            jump: self.here,
+                            line: self.line,
+                            has_else: false,
+                        });
+                        self.inst(0x10, 0x00)?;
+                    }
+                    other => {
+                        return Err(
+                            self.error(format!("Expected 'then' or 'begin', got '{}'", other))
+                        )
+                    }
+                }
+            }
+            "else" => {
+                let branch = self
+                    .branches
+                    .pop()
+                    .filter(|b| !b.has_else)
+                    .ok_or_else(|| self.error("'else' without a matching 'begin'"))?;
+                let jump = self.here;
+                self.inst(0x10, 0x00)?;
+                self.patch_jump(branch.jump, self.here)?;
+                self.branches.push(Branch {
+                    jump,
+                    line: self.line,
+                    has_else: true,
+                });
+            }
+            "end" => {
+                let branch = self
+                    .branches
+                    .pop()
+                    .ok_or_else(|| self.error("'end' without a matching 'begin'"))?;
+                self.patch_jump(branch.jump, self.here)?;
+            }
+            "loop" => self.loops.push(Loop {
+                start: self.here,
+                line: self.line,
+                exits: Vec::new(),
+            }),
+            "while" => {
+                if self.loops.is_empty() {
+                    return Err(self.error("'while' outside a loop"));
+                }
+                let condition = self.condition()?;
+                self.conditional(condition, true)?;
+                let exit = self.here;
+                self.inst(0x10, 0x00)?;
+                if let Some(l) = self.loops.last_mut() {
+                    l.exits.push(exit);
+                }
+            }
+            "again" => {
+                let l = self
+                    .loops
+                    .pop()
+                    .ok_or_else(|| self.error("'again' without a matching 'loop'"))?;
+                let jump = self.here;
+                self.inst(0x10, 0x00)?;
+                self.patch_jump(jump, l.start)?;
+                for exit in l.exits {
+                    self.patch_jump(exit, self.here)?;
+                }
+            }
+            "then" | "begin" => return Err(self.error(format!("'{}' without an 'if'", t))),
+            _ => {
+                if let Some(reg) = self.register_named(t) {
+                    return self.register_statement(reg);
+                }
+                if self.macros.contains_key(t) {
+                    return self.expand_macro(t);
+                }
+                if self.string_modes.contains_key(t) {
+                    return self.expand_string(t);
+                }
+                if let Some(n) = parse_number(t) {
+                    let b = self.check_byte(n)?;
+                    return self.byte(b);
+                }
+                if let Some(&n) = self.constants.get(t) {
+                    let b = self.check_byte(n.floor() as i64)?;
+                    return self.byte(b);
+                }
+                if t.starts_with(':') {
+                    return Err(self.error(format!("Unknown directive '{}'", t)));
+                }
+                // Any other name calls a subroutine.
+                self.pos -= 1;
+                self.wide_inst(0x2)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AssembleError> {
+        let op = self.expect_token()?.text;
+        match op.as_str() {
+            ":=" => match self.peek() {
+                Some("key") => {
+                    self.next();
+                    self.inst(0xF0 | x, 0x0A)
+                }
+                Some("delay") => {
+                    self.next();
+                    self.inst(0xF0 | x, 0x07)
+                }
+                Some("random") => {
+                    self.next();
+                    let mask = self.byte_value()?;
+                    self.inst(0xC0 | x, mask)
+                }
+                _ => match self.peek_register() {
+                    Some(y) => {
+                        self.next();
+                        self.inst(0x80 | x, y << 4)
+                    }
+                    None => {
+                        let n = self.byte_value()?;
+                        self.inst(0x60 | x, n)
+                    }
+                },
+            },
+            "+=" | "-=" if self.peek_register().is_none() => {
+                let n = self.byte_value()?;
+                let n = if op == "-=" { n.wrapping_neg() } else { n };
+                self.inst(0x70 | x, n)
+            }
+            _ => {
+                let n = match op.as_str() {
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    "+=" => 0x4,
+                    "-=" => 0x5,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    "<<=" => 0xE,
+                    _ => return Err(self.error(format!("Unknown operator '{}'", op))),
+                };
+                let y = self.register()?;
+                self.inst(0x80 | x, (y << 4) | n)
+            }
+        }
+    }
+
+    // The tokens of an `if` or `while` condition, read before anything is
+    // emitted since `if` only knows what to emit once it sees then or begin.
+    fn condition(&mut self) -> Result<Condition, AssembleError> {
+        let reg = self.register()?;
+        let op = self.expect_token()?.text;
+        let operand = match op.as_str() {
+            "key" | "-key" => None,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                let token = self.expect_token()?;
+                if token.text == "{" && !token.quoted {
+                    let n = self.calc()?.floor() as i64;
+                    Some(number_token(n, token.line))
+                } else {
+                    Some(token)
+                }
+            }
+            _ => return Err(self.error(format!("Unknown comparison '{}'", op))),
+        };
+        Ok(Condition { reg, op, operand })
+    }
+
+    // Emit code that skips the next instruction when the condition is false,
+    // or when it's true if `negated`.
+    fn conditional(&mut self, condition: Condition, negated: bool) -> Result<(), AssembleError> {
+        let Condition { reg, op, operand } = condition;
+        let op = match (negated, op.as_str()) {
+            (false, op) => op,
+            (true, "==") => "!=",
+            (true, "!=") => "==",
+            (true, "key") => "-key",
+            (true, "-key") => "key",
+            (true, "<") => ">=",
+            (true, ">") => "<=",
+            (true, "<=") => ">",
+            (true, ">=") => "<",
+            (true, op) => op,
+        };
+        let operand = match operand {
+            Some(token) => match self.register_named(&token.text).filter(|_| !token.quoted) {
+                Some(y) => Some(Operand::Register(y)),
+                None => {
+                    let value = self.token_value(&token)?;
+                    let n = self.known(value)?;
+                    Some(Operand::Byte(self.check_byte(n)?))
+                }
+            },
+            None => None,
+        };
+        let temp = self.alias("compare-temp", 0xF);
+        let load_temp = |asm: &mut Assembler, operand| match operand {
+            Some(Operand::Register(y)) => asm.inst(0x80 | temp, y << 4),
+            Some(Operand::Byte(n)) => asm.inst(0x60 | temp, n),
+            None => Ok(()),
+        };
+        match op {
+            "==" => match operand {
+                Some(Operand::Register(y)) => self.inst(0x90 | reg, y << 4),
+                Some(Operand::Byte(n)) => self.inst(0x40 | reg, n),
+                None => Ok(()),
+            },
+            "!=" => match operand {
+                Some(Operand::Register(y)) => self.inst(0x50 | reg, y << 4),
+                Some(Operand::Byte(n)) => self.inst(0x30 | reg, n),
+                None => Ok(()),
+            },
+            "key" => self.inst(0xE0 | reg, 0xA1),
+            "-key" => self.inst(0xE0 | reg, 0x9E),
+            // The rest subtract into the temporary register and test the
+            // borrow flag it ends up holding.
+            ">" => {
+                load_temp(self, operand)?;
+                self.inst(0x80 | temp, (reg << 4) | 0x5)?;
+                self.inst(0x3F, 0x01)
+            }
+            "<" => {
+                load_temp(self, operand)?;
+                self.inst(0x80 | temp, (reg << 4) | 0x7)?;
+                self.inst(0x3F, 0x01)
+            }
+            ">=" => {
+                load_temp(self, operand)?;
+                self.inst(0x80 | temp, (reg << 4) | 0x7)?;
+                self.inst(0x4F, 0x01)
+            }
+            "<=" => {
+                load_temp(self, operand)?;
+                self.inst(0x80 | temp, (reg << 4) | 0x5)?;
+                self.inst(0x4F, 0x01)
+            }
+            _ => Err(self.error(format!("Unknown comparison '{}'", op))),
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), AssembleError> {
+        let name = self.name()?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.expect_token()?;
+            if token.text == "{" && !token.quoted {
+                break;
+            }
+            params.push(token.text);
+        }
+        let body = self.block()?;
+        self.macros.insert(
+            name,
+            Macro {
+                params,
+                body,
+                calls: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn define_string_mode(&mut self) -> Result<(), AssembleError> {
+        let name = self.name()?;
+        let alphabet = self.expect_token()?;
+        if !alphabet.quoted {
+            return Err(self.error("Expected a string of characters"));
+        }
+        self.expect("{")?;
+        let body = self.block()?;
+        self.string_modes.entry(name).or_default().push(StringMode {
+            alphabet: alphabet.text.chars().collect(),
+            body,
+        });
+        Ok(())
+    }
+
+    // Tokens up to the `}` matching an already read `{`.
+    fn block(&mut self) -> Result<Vec<Token>, AssembleError> {
+        let start = self.line;
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self
+                .next()
+                .ok_or_else(|| error(start, "'{' without a matching '}'"))?;
+            if !token.quoted {
+                match token.text.as_str() {
+                    "{" => depth += 1,
+                    "}" if depth == 0 => return Ok(body),
+                    "}" => depth -= 1,
+                    _ => {}
+                }
+            }
+            body.push(token);
+        }
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), AssembleError> {
+        let line = self.line;
+        let count = self.macros[name].params.len();
+        let mut args = Vec::new();
+        for _ in 0..count {
+            args.push(self.expect_token()?);
+        }
+        let m = self.macros.get_mut(name).expect("macro exists");
+        let calls = m.calls;
+        m.calls += 1;
+        let expansion: Vec<Token> = m
+            .body
+            .iter()
+            .map(|token| {
+                if token.quoted {
+                    return token.clone();
+                }
+                if let Some(i) = m.params.iter().position(|p| *p == token.text) {
+                    return args[i].clone();
+                }
+                if token.text == "CALLS" {
+                    return number_token(calls as i64, line);
+                }
+                token.clone()
+            })
+            .collect();
+        self.splice(expansion)
+    }
+
+    fn expand_string(&mut self, name: &str) -> Result<(), AssembleError> {
+        let line = self.line;
+        let text = self.expect_token()?;
+        if !text.quoted {
+            return Err(self.error("Expected a string"));
+        }
+        let mut expansion = Vec::new();
+        for (index, c) in text.text.chars().enumerate() {
+            let (value, mode) = self.string_modes[name]
+                .iter()
+                .find_map(|mode| Some((mode.alphabet.iter().position(|&a| a == c)?, mode)))
+                .ok_or_else(|| error(line, format!("String mode '{}' has no '{}'", name, c)))?;
+            expansion.extend(mode.body.iter().map(|token| match token.text.as_str() {
+                _ if token.quoted => token.clone(),
+                "CHAR" => number_token(c as i64, line),
+                "INDEX" => number_token(index as i64, line),
+                "VALUE" => number_token(value as i64, line),
+                _ => token.clone(),
+            }));
+        }
+        self.splice(expansion)
+    }
+
+    fn splice(&mut self, tokens: Vec<Token>) -> Result<(), AssembleError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(self.error("Too many macro expansions, is a macro recursive?"));
+        }
+        self.tokens.splice(self.pos..self.pos, tokens);
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, AssembleError> {
+        let token = self.expect_token()?;
+        if token.quoted || parse_number(&token.text).is_some() {
+            return Err(self.error(format!("'{}' isn't a valid name", token.text)));
+        }
+        Ok(token.text)
+    }
+
+    fn define(&mut self, name: String, addr: usize) -> Result<(), AssembleError> {
+        if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+            return Err(self.error(format!("'{}' is already defined", name)));
+        }
+        if self.register_named(&name).is_some() {
+            return Err(self.error(format!("'{}' is a register", name)));
+        }
+        self.labels.insert(name, addr);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: String, value: f64) -> Result<(), AssembleError> {
+        if self.labels.contains_key(&name) {
+            return Err(self.error(format!("'{}' is already defined", name)));
+        }
+        self.constants.insert(name, value);
+        Ok(())
+    }
+
+    fn alias(&self, name: &str, default: u8) -> u8 {
+        self.aliases.get(name).copied().unwrap_or(default)
+    }
+
+    fn register_named(&self, name: &str) -> Option<u8> {
+        let mut chars = name.chars();
+        if let (Some('v' | 'V'), Some(digit), None) = (chars.next(), chars.next(), chars.next()) {
+            if let Some(reg) = digit.to_digit(16) {
+                return Some(reg as u8);
+            }
+        }
+        self.aliases.get(name).copied()
+    }
+
+    fn peek_register(&self) -> Option<u8> {
+        let token = self.tokens.get(self.pos)?;
+        if token.quoted {
+            return None;
+        }
+        self.register_named(&token.text)
+    }
+
+    fn register(&mut self) -> Result<u8, AssembleError> {
+        let token = self.expect_token()?;
+        match self.register_named(&token.text) {
+            Some(reg) if !token.quoted => Ok(reg),
+            _ => Err(self.error(format!("Expected a register, got '{}'", token.text))),
+        }
+    }
+
+    fn reg_inst(&mut self, low: u8) -> Result<(), AssembleError> {
+        let x = self.register()?;
+        self.inst(0xF0 | x, low)
+    }
+
+    fn value(&mut self) -> Result<Value, AssembleError> {
+        let token = self.expect_token()?;
+        self.token_value(&token)
+    }
+
+    fn token_value(&mut self, token: &Token) -> Result<Value, AssembleError> {
+        let t = token.text.as_str();
+        if token.quoted {
+            return Err(self.error(format!("Expected a number, got \"{}\"", t)));
+        }
+        if t == "{" {
+            return Ok(Value::Known(self.calc()?.floor() as i64));
+        }
+        if let Some(n) = parse_number(t) {
+            return Ok(Value::Known(n));
+        }
+        if let Some(n) = self.constants.get(t) {
+            return Ok(Value::Known(n.floor() as i64));
+        }
+        if let Some(&addr) = self.labels.get(t) {
+            return Ok(Value::Known(addr as i64));
+        }
+        if self.register_named(t).is_some() {
+            return Err(self.error(format!("Expected a number, got register '{}'", t)));
+        }
+        Ok(Value::Forward(t.to_string()))
+    }
+
+    fn known(&self, value: Value) -> Result<i64, AssembleError> {
+        match value {
+            Value::Known(n) => Ok(n),
+            Value::Forward(name) => Err(self.error(format!("Undefined name '{}'", name))),
+        }
+    }
+
+    fn known_value(&mut self) -> Result<i64, AssembleError> {
+        let value = self.value()?;
+        self.known(value)
+    }
+
+    fn check_byte(&self, n: i64) -> Result<u8, AssembleError> {
+        if !(-128..=255).contains(&n) {
+            return Err(self.error(format!("{} doesn't fit in a byte", n)));
+        }
+        Ok(n as u8)
+    }
+
+    fn byte_value(&mut self) -> Result<u8, AssembleError> {
+        let n = self.known_value()?;
+        self.check_byte(n)
+    }
+
+    fn tiny_value(&mut self) -> Result<u8, AssembleError> {
+        let n = self.known_value()?;
+        if !(0..=15).contains(&n) {
+            return Err(self.error(format!("{} doesn't fit in 4 bits", n)));
+        }
+        Ok(n as u8)
+    }
+
+    // An instruction with a 12-bit address, `op` being its top nybble.
+    fn wide_inst(&mut self, op: u8) -> Result<(), AssembleError> {
+        let at = self.here;
+        let value = self.value()?;
+        self.inst(op << 4, 0x00)?;
+        self.resolve(at, value, PatchKind::Wide)
+    }
+
+    fn long_word(&mut self) -> Result<(), AssembleError> {
+        let at = self.here;
+        let value = self.value()?;
+        self.inst(0x00, 0x00)?;
+        self.resolve(at, value, PatchKind::Long)
+    }
+
+    // Fill in `value` now if it's known, otherwise once its label is defined.
+    fn resolve(&mut self, addr: usize, value: Value, kind: PatchKind) -> Result<(), AssembleError> {
+        match value {
+            Value::Known(n) => self.patch(addr, n, kind),
+            Value::Forward(name) => {
+                self.patches.push(Patch {
+                    addr,
+                    name,
+                    line: self.line,
+                    kind,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn patch(&mut self, addr: usize, n: i64, kind: PatchKind) -> Result<(), AssembleError> {
+        let max = match kind {
+            PatchKind::Wide | PatchKind::Unpack => 0xFFF,
+            PatchKind::Long | PatchKind::UnpackLong => 0xFFFF,
+        };
+        if !(0..=max).contains(&n) {
+            return Err(self.error(format!("Address {:#X} is out of range", n)));
+        }
+        let (hi, lo) = ((n >> 8) as u8, n as u8);
+        let memory = &mut self.memory;
+        match kind {
+            PatchKind::Wide => {
+                memory[addr] = (memory[addr] & 0xF0) | hi;
+                memory[addr + 1] = lo;
+            }
+            PatchKind::Long => {
+                memory[addr] = hi;
+                memory[addr + 1] = lo;
+            }
+            PatchKind::Unpack => {
+                memory[addr + 1] = (memory[addr + 1] & 0xF0) | hi;
+                memory[addr + 3] = lo;
+            }
+            PatchKind::UnpackLong => {
+                memory[addr + 1] = hi;
+                memory[addr + 3] = lo;
+            }
+        }
+        Ok(())
+    }
+
+    fn patch_jump(&mut self, addr: usize, target: usize) -> Result<(), AssembleError> {
+        self.patch(addr, target as i64, PatchKind::Wide)
+    }
+
+    // Evaluate a `:calc` expression up to its closing `}`. Like Octo, there's
+    // no precedence: operators apply right to left unless parenthesized.
+    fn calc(&mut self) -> Result<f64, AssembleError> {
+        let tokens = self.block()?;
+        let mut pos = 0;
+        let value = self.expression(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return Err(self.error(format!("Unexpected '{}' in expression", tokens[pos].text)));
+        }
+        Ok(value)
+    }
+
+    fn expression(&self, tokens: &[Token], pos: &mut usize) -> Result<f64, AssembleError> {
+        let lhs = self.term(tokens, pos)?;
+        let Some(op) = tokens.get(*pos).filter(|t| !t.quoted) else {
+            return Ok(lhs);
+        };
+        let op = op.text.as_str();
+        if op == ")" {
+            return Ok(lhs);
+        }
+        *pos += 1;
+        let rhs = self.expression(tokens, pos)?;
+        let (a, b) = (lhs as i64, rhs as i64);
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        Ok(match op {
+            "+" => lhs + rhs,
+            "-" => lhs - rhs,
+            "*" => lhs * rhs,
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "pow" => lhs.powf(rhs),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.checked_shl(b as u32).unwrap_or(0) as f64,
+            ">>" => a.checked_shr(b as u32).unwrap_or(0) as f64,
+            "<" => flag(lhs < rhs),
+            "<=" => flag(lhs <= rhs),
+            "==" => flag(lhs == rhs),
+            "!=" => flag(lhs != rhs),
+            ">=" => flag(lhs >= rhs),
+            ">" => flag(lhs > rhs),
+            _ => return Err(self.error(format!("Unknown operator '{}'", op))),
+        })
+    }
+
+    fn term(&self, tokens: &[Token], pos: &mut usize) -> Result<f64, AssembleError> {
+        let token = tokens
+            .get(*pos)
+            .ok_or_else(|| self.error("Expression ended early"))?;
+        *pos += 1;
+        if token.quoted {
+            return Err(self.error(format!("Expected a number, got \"{}\"", token.text)));
+        }
+        let t = token.text.as_str();
+        let unary = |f: fn(f64) -> f64, pos: &mut usize| Ok(f(self.term(tokens, pos)?));
+        match t {
+            "(" => {
+                let value = self.expression(tokens, pos)?;
+                match tokens.get(*pos) {
+                    Some(t) if t.text == ")" && !t.quoted => {
+                        *pos += 1;
+                        Ok(value)
+                    }
+                    _ => Err(self.error("'(' without a matching ')'")),
+                }
+            }
+            "-" => unary(|x| -x, pos),
+            "~" => unary(|x| !(x as i64) as f64, pos),
+            "!" => unary(|x| if x == 0.0 { 1.0 } else { 0.0 }, pos),
+            "sin" => unary(f64::sin, pos),
+            "cos" => unary(f64::cos, pos),
+            "tan" => unary(f64::tan, pos),
+            "exp" => unary(f64::exp, pos),
+            "log" => unary(f64::ln, pos),
+            "abs" => unary(f64::abs, pos),
+            "sqrt" => unary(f64::sqrt, pos),
+            "sign" => unary(f64::signum, pos),
+            "ceil" => unary(f64::ceil, pos),
+            "floor" => unary(f64::floor, pos),
+            "@" => {
+                let addr = self.term(tokens, pos)? as i64;
+                let byte = usize::try_from(addr)
+                    .ok()
+                    .and_then(|addr| self.memory.get(addr))
+                    .ok_or_else(|| self.error(format!("Address {} is outside memory", addr)))?;
+                Ok(*byte as f64)
+            }
+            "HERE" => Ok(self.here as f64),
+            "PI" => Ok(core::f64::consts::PI),
+            "E" => Ok(core::f64::consts::E),
+            _ => {
+                if let Some(n) = parse_number(t) {
+                    Ok(n as f64)
+                } else if let Some(&n) = self.constants.get(t) {
+                    Ok(n)
+                } else if let Some(&addr) = self.labels.get(t) {
+                    Ok(addr as f64)
+                } else {
+                    Err(self.error(format!("Undefined name '{}'", t)))
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AssembleError> {
+        if let Some(l) = self.loops.last() {
+            return Err(error(l.line, "'loop' without a matching 'again'"));
+        }
+        if let Some(b) = self.branches.last() {
+            return Err(error(b.line, "'begin' without a matching 'end'"));
+        }
+        let main = *self
+            .labels
+            .get("main")
+            .ok_or_else(|| self.error("The program has no main label"))?;
+        if self.jump_to_main {
+            self.memory[START] = 0x10;
+            self.patch(START, main as i64, PatchKind::Wide)?;
+        }
+        for patch in core::mem::take(&mut self.patches) {
+            self.line = patch.line;
+            let addr = *self
+                .labels
+                .get(&patch.name)
+                .ok_or_else(|| self.error(format!("Undefined name '{}'", patch.name)))?;
+            self.patch(patch.addr, addr as i64, patch.kind)?;
+        }
+        self.memory.truncate(self.end);
+        Ok(self.memory.split_off(START))
+    }
+}
+
+fn number_token(n: i64, line: usize) -> Token {
+    Token {
+        text: n.to_string(),
+        line,
+        quoted: false,
+    }
+}
+
+fn parse_number(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let n = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -n } else { n })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::Chip8VM;
+
+    fn run(source: &str, cycles: usize) -> Chip8VM {
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&assemble(source).unwrap()).unwrap();
+        for _ in 0..cycles {
+            vm.cycle().unwrap();
+        }
+        vm
+    }
+
+    fn assert_error(source: &str, line: usize, message: &str) {
+        let err = assemble(source).unwrap_err();
+        assert_eq!(err.line, line, "{}", err);
+        assert!(err.message.contains(message), "{}", err);
+    }
+
+    #[test]
+    fn test_main() {
+        assert_eq!(assemble(": main loop again").unwrap(), [0x12, 0x00]);
+        // Anything before main needs the jump.
+        assert_eq!(
+            assemble(": helper return : main helper").unwrap(),
+            [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]
+        );
+        assert_error("v0 := 1", 1, "no main label");
+    }
+
+    #[test]
+    fn test_forward_references() {
+        let source = "
+            : draw
+              i := sprite-data  # not defined yet
+              sprite v0 v1 4
+              return
+            : main
+              draw
+              loop again
+            : sprite-data
+              0xF0 0x90 0x90 0xF0
+        ";
+        #[rustfmt::skip]
+        let expected = [
+            0x12, 0x08, 0xA2, 0x0C, 0xD0, 0x14, 0x00, 0xEE,
+            0x22, 0x02, 0x12, 0x0A, 0xF0, 0x90, 0x90, 0xF0,
+        ];
+        assert_eq!(assemble(source).unwrap(), expected);
+        assert_error(": main\n  jump nowhere\n", 2, "Undefined name 'nowhere'");
+    }
+
+    #[test]
+    fn test_instructions() {
+        let source = "
+            : main
+              v0 := 5  v1 := v0  v2 += 3  v2 -= 1  v3 += v1  v3 -= v1  v3 =- v1
+              v4 |= v5  v4 &= v5  v4 ^= v5  v4 >>= v5  v4 <<= v5
+              v6 := random 0x0F  v7 := key  v7 := delay  delay := v7  buzzer := v7
+              i := hex v1  i += v2  bcd v3  save v4  load v4  clear  ;
+              jump0 0x300  :call 0x400  native 0x123  i := 0xABC  jump 0x200
+              save v1 - v3  load v2 - v4  hires  lores  scroll-down 3  plane 2
+              i := long 0x1234  :byte -1  :byte 0b101
+        ";
+        #[rustfmt::skip]
+        let expected = [
+            0x60, 0x05, 0x81, 0x00, 0x72, 0x03, 0x72, 0xFF, 0x83, 0x14, 0x83, 0x15, 0x83, 0x17,
+            0x84, 0x51, 0x84, 0x52, 0x84, 0x53, 0x84, 0x56, 0x84, 0x5E,
+            0xC6, 0x0F, 0xF7, 0x0A, 0xF7, 0x07, 0xF7, 0x15, 0xF7, 0x18,
+            0xF1, 0x29, 0xF2, 0x1E, 0xF3, 0x33, 0xF4, 0x55, 0xF4, 0x65, 0x00, 0xE0, 0x00, 0xEE,
+            0xB3, 0x00, 0x24, 0x00, 0x01, 0x23, 0xAA, 0xBC, 0x12, 0x00,
+            0x51, 0x32, 0x52, 0x43, 0x00, 0xFF, 0x00, 0xFE, 0x00, 0xC3, 0xF2, 0x01,
+            0xF0, 0x00, 0x12, 0x34, 0xFF, 0x05,
+        ];
+        assert_eq!(assemble(source).unwrap(), expected);
+
+        assert_error(": main\nv0 := 256", 2, "doesn't fit in a byte");
+        assert_error(": main\nsprite v0 v1 16", 2, "doesn't fit in 4 bits");
+        assert_error(": main\njump 0x1000", 2, "out of range");
+        assert_error(": main\nv0 := i", 2, "Undefined name 'i'");
+        assert_error(": main\n:frobnicate", 2, "Unknown directive");
+        assert_error(": main\n\"oops", 2, "Unterminated string");
+        assert_error(": main\n: main", 2, "already defined");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "
+            : main
+              if v0 == 5 then v1 := 1
+              if v0 != v2 then v1 := 2
+              if v3 key then clear
+              if v3 -key then clear
+              if v0 > 10 begin
+                v1 := 3
+              else
+                v1 := 4
+              end
+              loop
+                v0 += 1
+                while v0 != 8
+              again
+        ";
+        #[rustfmt::skip]
+        let expected = [
+            0x40, 0x05, 0x61, 0x01, 0x50, 0x20, 0x61, 0x02,
+            0xE3, 0xA1, 0x00, 0xE0, 0xE3, 0x9E, 0x00, 0xE0,
+            0x6F, 0x0A, 0x8F, 0x05, 0x4F, 0x01, 0x12, 0x1C, 0x61, 0x03, 0x12, 0x1E, 0x61, 0x04,
+            0x70, 0x01, 0x40, 0x08, 0x12, 0x26, 0x12, 0x1E,
+        ];
+        assert_eq!(assemble(source).unwrap(), expected);
+
+        // The loop counts up to 8 then falls out of it.
+        let vm = run(&format!("{}\n v5 := 1 loop again", source), 40);
+        assert_eq!(vm.registers()[0], 8);
+        assert_eq!(vm.registers()[5], 1);
+
+        assert_error(": main\nloop\nv0 += 1", 2, "without a matching 'again'");
+        assert_error(": main\nif v0 == 1 begin\n", 2, "without a matching 'end'");
+        assert_error(": main\nelse", 2, "'else' without");
+        assert_error(": main\nagain", 2, "'again' without");
+        assert_error(": main\nwhile v0 == 1", 2, "outside a loop");
+        assert_error(": main\nif v0 < 1 clear", 2, "Expected 'then' or 'begin'");
+    }
+
+    type Comparison = fn(&u8, &u8) -> bool;
+
+    #[test]
+    fn test_comparisons() {
+        for (a, b) in [(3, 7), (7, 3), (5, 5), (0, 255), (255, 0)] {
+            let ops: [(&str, Comparison); 6] = [
+                ("==", u8::eq),
+                ("!=", u8::ne),
+                ("<", u8::lt),
+                (">", u8::gt),
+                ("<=", u8::le),
+                (">=", u8::ge),
+            ];
+            for (op, holds) in ops {
+                for rhs in ["v1", &b.to_string()] {
+                    let source = format!(
+                        ": main v0 := {a} v1 := {b}
+                           if v0 {op} {rhs} then v2 := 1
+                           if v0 {op} {rhs} begin v3 := 1 else v3 := 2 end
+                           loop while v0 {op} {rhs} v4 := 1 v0 := {b} v0 ^= v0 again
+                           : done loop again"
+                    );
+                    let vm = run(&source, 40);
+                    let expected = holds(&a, &b);
+                    let what = format!("{} {} {}", a, op, rhs);
+                    assert_eq!(vm.registers()[2] == 1, expected, "then: {}", what);
+                    assert_eq!(
+                        vm.registers()[3],
+                        if expected { 1 } else { 2 },
+                        "begin: {}",
+                        what
+                    );
+                    assert_eq!(vm.registers()[4] == 1, expected, "while: {}", what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_directives() {
+        let source = "
+            :alias score v5
+            :const lives 3
+            :calc no-precedence { 2 * 3 + 1 }
+            :calc grouped { ( 2 * 3 ) + 1 }
+            : main
+              score := lives
+              score += score
+              :next target
+              v0 := 0
+              i := target
+              :unpack 0xA data
+              :byte no-precedence
+              :byte grouped
+              :byte { HERE - 0x200 }
+              :pointer data
+              jump far
+            : data 1 2
+            :org 0x300
+            : far loop again
+        ";
+        #[rustfmt::skip]
+        let expected = [
+            0x65, 0x03, 0x85, 0x54, 0x60, 0x00, 0xA2, 0x05,
+            0x60, 0xA2, 0x61, 0x13, 0x08, 0x07, 0x0E, 0x02, 0x13, 0x13, 0x00, 0x01, 0x02,
+        ];
+        let rom = assemble(source).unwrap();
+        assert_eq!(rom[..expected.len()], expected);
+        assert_eq!(rom.len(), 0x102);
+        assert_eq!(rom[0x100..], [0x13, 0x00]);
+
+        assert_error(":assert \"too big\" { 1 > 2 }\n: main", 1, "too big");
+        assert!(assemble(":assert { 2 > 1 }\n: main").is_ok());
+        assert_error(":calc x { 1 + }\n: main", 1, "ended early");
+        assert_error(":calc x { nope }\n: main", 1, "Undefined name 'nope'");
+    }
+
+    #[test]
+    fn test_macros() {
+        let source = r#"
+            :macro set reg val { reg := val }
+            :macro counter { :byte CALLS }
+            :stringmode text "ABC" { :byte { VALUE + 1 } }
+            :stringmode text "xy" { :byte CHAR }
+            : main
+              set v3 7
+              counter counter
+              text "CAyB"
+        "#;
+        assert_eq!(
+            assemble(source).unwrap(),
+            [0x63, 0x07, 0x00, 0x01, 0x03, 0x01, b'y', 0x02]
+        );
+
+        assert_error(
+            ":stringmode text \"AB\" { :byte VALUE }\n: main\ntext \"AZ\"",
+            3,
+            "has no 'Z'",
+        );
+        assert_error(
+            ":macro forever { forever }\n: main\nforever",
+            1,
+            "Too many macro",
+        );
+        assert_error(":macro open {\n: main", 1, "without a matching '}'");
+    }
+}
@@ -0,0 +1,164 @@
+/// A single RGBA color, laid out the way frame buffers expect it.
+pub type Rgba = [u8; 4];
+
+/// Colors used to turn display pixels into RGBA.
+///
+/// Palettes always carry four colors so they are ready for multi-plane displays:
+/// index 0 is the background, 1 is the first plane, 2 the second plane and 3 is
+/// used where both planes overlap. The single-plane display only uses 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgba; 4],
+}
+
+impl Palette {
+    /// Purple on black, the emulator's original look.
+    pub const PURPLE: Palette = Palette::from_hex([0x000000, 0x5e48e8, 0x2f2474, 0xaea3f3]);
+    /// Green phosphor monitor.
+    pub const GREEN: Palette = Palette::from_hex([0x0a1a0a, 0x33ff66, 0x1a8033, 0x99ffb3]);
+    /// Amber phosphor monitor.
+    pub const AMBER: Palette = Palette::from_hex([0x1a1000, 0xffb000, 0x805800, 0xffd780]);
+    /// Greenish LCD, dark pixels on a light background.
+    pub const LCD: Palette = Palette::from_hex([0x9bbc0f, 0x0f380f, 0x306230, 0x8bac0f]);
+    /// White on black for readability.
+    pub const HIGH_CONTRAST: Palette = Palette::from_hex([0x000000, 0xffffff, 0xffff00, 0x00ffff]);
+    /// The defaults used by the Octo IDE.
+    pub const OCTO: Palette = Palette::from_hex([0x996600, 0xffcc00, 0xff6600, 0x662200]);
+
+    const NAMED: [(&'static str, Palette); 6] = [
+        ("purple", Palette::PURPLE),
+        ("green", Palette::GREEN),
+        ("amber", Palette::AMBER),
+        ("lcd", Palette::LCD),
+        ("high-contrast", Palette::HIGH_CONTRAST),
+        ("octo", Palette::OCTO),
+    ];
+
+    /// Build a palette from `0xRRGGBB` values, all fully opaque.
+    pub const fn from_hex(colors: [u32; 4]) -> Palette {
+        Palette {
+            colors: [
+                hex_to_rgba(colors[0]),
+                hex_to_rgba(colors[1]),
+                hex_to_rgba(colors[2]),
+                hex_to_rgba(colors[3]),
+            ],
+        }
+    }
+
+    /// Look up a built-in palette by name, ignoring case.
+    pub fn named(name: &str) -> Option<Palette> {
+        Palette::NAMED
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, p)| *p)
+    }
+
+    /// Names accepted by `Palette::named`.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        Palette::NAMED.iter().map(|(n, _)| *n)
+    }
+
+    pub fn background(&self) -> Rgba {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgba {
+        self.colors[1]
+    }
+
+    pub fn with_background(mut self, color: Rgba) -> Palette {
+        self.colors[0] = color;
+        self
+    }
+
+    pub fn with_foreground(mut self, color: Rgba) -> Palette {
+        self.colors[1] = color;
+        self
+    }
+
+    /// Color for a pixel value, where each bit is one display plane.
+    pub fn color(&self, index: u8) -> Rgba {
+        self.colors[(index & 0x3) as usize]
+    }
+
+    /// Blend from background (0) to foreground (255) for faded pixels.
+    pub fn shade(&self, intensity: u8) -> Rgba {
+        let (bg, fg) = (self.background(), self.foreground());
+        let mut out = [0; 4];
+        for c in 0..4 {
+            let (b, f, t) = (bg[c] as u32, fg[c] as u32, intensity as u32);
+            out[c] = ((b * (255 - t) + f * t) / 255) as u8;
+        }
+        out
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::PURPLE
+    }
+}
+
+const fn hex_to_rgba(hex: u32) -> Rgba {
+    [(hex >> 16) as u8, (hex >> 8) as u8, hex as u8, 0xff]
+}
+
+/// Parse a `#RRGGBB` / `RRGGBB` / `0xRRGGBB` color string.
+pub fn parse_color(s: &str) -> Option<Rgba> {
+    let s = s.trim();
+    let hex = s
+        .strip_prefix('#')
+        .or_else(|| s.strip_prefix("0x"))
+        .unwrap_or(s);
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(hex_to_rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_palettes() {
+        assert_eq!(Palette::named("purple"), Some(Palette::PURPLE));
+        assert_eq!(
+            Palette::named("High-Contrast"),
+            Some(Palette::HIGH_CONTRAST)
+        );
+        assert_eq!(Palette::named("nope"), None);
+        for name in Palette::names() {
+            assert!(Palette::named(name).is_some());
+        }
+        assert_eq!(Palette::default().foreground(), [0x5e, 0x48, 0xe8, 0xff]);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff8000"), Some([0xff, 0x80, 0x00, 0xff]));
+        assert_eq!(parse_color("0x102030"), Some([0x10, 0x20, 0x30, 0xff]));
+        assert_eq!(parse_color("abcdef"), Some([0xab, 0xcd, 0xef, 0xff]));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("zzzzzz"), None);
+    }
+
+    #[test]
+    fn test_custom_colors() {
+        let p = Palette::GREEN
+            .with_foreground([1, 2, 3, 255])
+            .with_background([4, 5, 6, 255]);
+        assert_eq!(p.color(1), [1, 2, 3, 255]);
+        assert_eq!(p.color(0), [4, 5, 6, 255]);
+        assert_eq!(p.color(2), Palette::GREEN.colors[2]);
+    }
+
+    #[test]
+    fn test_shade() {
+        let p = Palette::HIGH_CONTRAST;
+        assert_eq!(p.shade(0), p.background());
+        assert_eq!(p.shade(255), p.foreground());
+        assert_eq!(p.shade(128), [128, 128, 128, 255]);
+    }
+}
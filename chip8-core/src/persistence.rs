@@ -0,0 +1,195 @@
+use crate::display::Display;
+
+const NUM_PIXELS: usize = Display::WIDTH * Display::HEIGHT;
+
+/// How pixels linger on screen after the VM turns them off.
+///
+/// CHIP-8 draws by XORing sprites, so moving objects are erased and redrawn
+/// every frame. Without some persistence they flicker badly on modern displays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PersistenceMode {
+    /// Show the frame buffer as-is.
+    #[default]
+    Off,
+    /// Exponential phosphor decay, keeping `retain`/255 of a pixel's intensity each frame.
+    Decay { retain: u8 },
+    /// OR the current frame with the previous one.
+    Blend,
+    /// Keep a pixel lit until it has been off for `frames` frames.
+    Hold { frames: u8 },
+}
+
+impl PersistenceMode {
+    /// 75%, what `decay` without a percentage uses.
+    pub const DEFAULT_RETAIN: u8 = 191;
+    pub const DEFAULT_HOLD_FRAMES: u8 = 2;
+
+    /// Parse `off`, `blend`, `decay[:percent]` or `hold[:frames]`.
+    pub fn parse(s: &str) -> Option<PersistenceMode> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name, arg) {
+            ("off", None) => Some(PersistenceMode::Off),
+            ("blend", None) => Some(PersistenceMode::Blend),
+            ("decay", None) => Some(PersistenceMode::Decay {
+                retain: PersistenceMode::DEFAULT_RETAIN,
+            }),
+            ("decay", Some(percent)) => {
+                let percent: u32 = percent.parse().ok().filter(|p| *p <= 100)?;
+                Some(PersistenceMode::Decay {
+                    retain: (percent * 255 / 100) as u8,
+                })
+            }
+            ("hold", None) => Some(PersistenceMode::Hold {
+                frames: PersistenceMode::DEFAULT_HOLD_FRAMES,
+            }),
+            ("hold", Some(frames)) => frames
+                .parse()
+                .ok()
+                .map(|frames| PersistenceMode::Hold { frames }),
+            _ => None,
+        }
+    }
+}
+
+/// Rendering stage that sits between the VM's frame buffer and the frontend.
+///
+/// Each call to `apply` should correspond to one displayed frame. The output is
+/// a per-pixel intensity where 0 is background and 255 is fully lit.
+pub struct Persistence {
+    mode: PersistenceMode,
+    intensity: [u8; NUM_PIXELS],
+    previous: [bool; NUM_PIXELS],
+    // frames since each pixel was last lit, used by `Hold`. Wider than
+    // `frames` so a pixel held for 255 frames still goes out.
+    off_frames: [u16; NUM_PIXELS],
+}
+
+impl Persistence {
+    pub fn new(mode: PersistenceMode) -> Persistence {
+        Persistence {
+            mode,
+            intensity: [0; NUM_PIXELS],
+            previous: [false; NUM_PIXELS],
+            off_frames: [u16::MAX; NUM_PIXELS],
+        }
+    }
+
+    pub fn mode(&self) -> PersistenceMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PersistenceMode) {
+        *self = Persistence::new(mode);
+    }
+
+    pub fn apply(&mut self, frame: &[bool]) -> &[u8] {
+        for (i, &lit) in frame.iter().enumerate().take(NUM_PIXELS) {
+            self.intensity[i] = match self.mode {
+                PersistenceMode::Off => on_off(lit),
+                PersistenceMode::Decay { retain } => {
+                    if lit {
+                        u8::MAX
+                    } else {
+                        ((self.intensity[i] as u16 * retain as u16) / 255) as u8
+                    }
+                }
+                PersistenceMode::Blend => on_off(lit || self.previous[i]),
+                PersistenceMode::Hold { frames } => {
+                    self.off_frames[i] = if lit {
+                        0
+                    } else {
+                        self.off_frames[i].saturating_add(1)
+                    };
+                    on_off(self.off_frames[i] <= u16::from(frames))
+                }
+            };
+            self.previous[i] = lit;
+        }
+        &self.intensity
+    }
+}
+
+fn on_off(lit: bool) -> u8 {
+    if lit {
+        u8::MAX
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with(lit: bool) -> [bool; NUM_PIXELS] {
+        let mut frame = [false; NUM_PIXELS];
+        frame[0] = lit;
+        frame
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(PersistenceMode::parse("off"), Some(PersistenceMode::Off));
+        assert_eq!(
+            PersistenceMode::parse("blend"),
+            Some(PersistenceMode::Blend)
+        );
+        assert_eq!(
+            PersistenceMode::parse("decay:50"),
+            Some(PersistenceMode::Decay { retain: 127 })
+        );
+        assert_eq!(
+            PersistenceMode::parse("hold:4"),
+            Some(PersistenceMode::Hold { frames: 4 })
+        );
+        assert_eq!(
+            PersistenceMode::parse("decay"),
+            PersistenceMode::parse("decay:75")
+        );
+        assert_eq!(PersistenceMode::parse("decay:101"), None);
+        assert_eq!(PersistenceMode::parse("blend:1"), None);
+        assert_eq!(PersistenceMode::parse("glow"), None);
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut p = Persistence::new(PersistenceMode::Decay { retain: 127 });
+        assert_eq!(p.apply(&frame_with(true))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 127);
+        assert_eq!(p.apply(&frame_with(false))[0], 63);
+        assert_eq!(p.apply(&frame_with(true))[0], 255);
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut p = Persistence::new(PersistenceMode::Blend);
+        assert_eq!(p.apply(&frame_with(true))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut p = Persistence::new(PersistenceMode::Hold { frames: 2 });
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+        assert_eq!(p.apply(&frame_with(true))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 255);
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+    }
+
+    #[test]
+    fn test_hold_max() {
+        let mut p = Persistence::new(PersistenceMode::Hold { frames: u8::MAX });
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+        assert_eq!(p.apply(&frame_with(true))[0], 255);
+        for _ in 0..255 {
+            assert_eq!(p.apply(&frame_with(false))[0], 255);
+        }
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+        assert_eq!(p.apply(&frame_with(false))[0], 0);
+    }
+}
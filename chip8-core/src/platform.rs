@@ -0,0 +1,118 @@
+use core::fmt;
+
+use crate::font::Font;
+use crate::quirks::Quirks;
+
+/// The machine a ROM was written for. Platforms differ in where programs are
+/// loaded and how much memory they have.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// The original RCA COSMAC VIP interpreter.
+    #[default]
+    CosmacVip,
+    /// CHIP-48 on the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1 on the HP-48.
+    SuperChip,
+    /// The DREAM 6800 hobbyist computer.
+    Dream6800,
+    /// The ETI-660, which loads programs at 0x600.
+    Eti660,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 5] = [
+        Platform::CosmacVip,
+        Platform::Chip48,
+        Platform::SuperChip,
+        Platform::Dream6800,
+        Platform::Eti660,
+    ];
+
+    /// Address programs are loaded at and execution starts from.
+    pub fn load_address(&self) -> u16 {
+        match self {
+            Platform::Eti660 => 0x600,
+            _ => 0x200,
+        }
+    }
+
+    /// Addressable memory in bytes.
+    pub fn memory_size(&self) -> usize {
+        4 * 1024
+    }
+
+    /// Largest ROM that fits between the load address and the end of memory.
+    pub fn max_rom_size(&self) -> usize {
+        self.memory_size() - self.load_address() as usize
+    }
+
+    /// How the platform's interpreter behaves.
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Platform::CosmacVip | Platform::Dream6800 | Platform::Eti660 => Quirks::VIP,
+            Platform::Chip48 => Quirks::CHIP48,
+            Platform::SuperChip => Quirks::SUPER_CHIP,
+        }
+    }
+
+    /// Nested subroutine calls the interpreter allowed.
+    pub fn stack_depth(&self) -> usize {
+        match self {
+            Platform::CosmacVip | Platform::Dream6800 | Platform::Eti660 => 12,
+            Platform::Chip48 | Platform::SuperChip => 16,
+        }
+    }
+
+    /// The hex digit sprites the platform's interpreter shipped with.
+    pub fn font(&self) -> Font {
+        match self {
+            Platform::CosmacVip => Font::VIP,
+            Platform::Chip48 => Font::CHIP48,
+            Platform::SuperChip => Font::SUPER_CHIP,
+            Platform::Dream6800 => Font::DREAM6800,
+            Platform::Eti660 => Font::ETI660,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::CosmacVip => "vip",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip => "schip",
+            Platform::Dream6800 => "dream6800",
+            Platform::Eti660 => "eti660",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Platform> {
+        Platform::ALL.into_iter().find(|p| p.name() == s)
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_address() {
+        assert_eq!(Platform::default().load_address(), 0x200);
+        assert_eq!(Platform::default().max_rom_size(), 3584);
+        assert_eq!(Platform::Eti660.load_address(), 0x600);
+        assert_eq!(Platform::Eti660.max_rom_size(), 2560);
+    }
+
+    #[test]
+    fn test_parse() {
+        for platform in Platform::ALL {
+            assert_eq!(Platform::parse(platform.name()), Some(platform));
+        }
+        assert_eq!(Platform::parse("xo-chip"), None);
+    }
+}
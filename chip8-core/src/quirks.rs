@@ -0,0 +1,110 @@
+/// Behaviors that differ between CHIP-8 interpreters. Names follow the
+/// community chip-8-database.
+///
+/// The default matches what this VM has always done, which is closest to
+/// SUPER-CHIP. Use `Platform::quirks` for a specific machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quirks {
+    /// 8XY6/8XYE shift VX in place instead of shifting VY into VX.
+    pub shift: bool,
+    /// FX55/FX65 add X to I instead of X + 1.
+    pub memory_increment_by_x: bool,
+    /// FX55/FX65 leave I unchanged. Takes priority over `memory_increment_by_x`.
+    pub memory_leave_i_unchanged: bool,
+    /// Sprites wrap around the screen edges instead of being clipped.
+    pub wrap: bool,
+    /// BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jump: bool,
+    /// DXYN waits for the next 60Hz timer tick before continuing.
+    pub vblank: bool,
+    /// 8XY1/8XY2/8XY3 reset VF to 0.
+    pub logic: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift: true,
+            memory_increment_by_x: false,
+            memory_leave_i_unchanged: true,
+            wrap: true,
+            jump: false,
+            vblank: false,
+            logic: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// Names for `get_mut`, shared by command lines and config files. The
+    /// chip-8-database's camelCase names, in kebab-case.
+    pub const NAMES: [&'static str; 7] = [
+        "shift",
+        "memory-increment-by-x",
+        "memory-leave-i-unchanged",
+        "wrap",
+        "jump",
+        "vblank",
+        "logic",
+    ];
+
+    /// The quirk called `name`, one of `NAMES`.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift" => Some(&mut self.shift),
+            "memory-increment-by-x" => Some(&mut self.memory_increment_by_x),
+            "memory-leave-i-unchanged" => Some(&mut self.memory_leave_i_unchanged),
+            "wrap" => Some(&mut self.wrap),
+            "jump" => Some(&mut self.jump),
+            "vblank" => Some(&mut self.vblank),
+            "logic" => Some(&mut self.logic),
+            _ => None,
+        }
+    }
+
+    /// The original COSMAC VIP interpreter.
+    pub const VIP: Quirks = Quirks {
+        shift: false,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: false,
+        wrap: false,
+        jump: false,
+        vblank: true,
+        logic: true,
+    };
+
+    pub const CHIP48: Quirks = Quirks {
+        shift: true,
+        memory_increment_by_x: true,
+        memory_leave_i_unchanged: false,
+        wrap: false,
+        jump: true,
+        vblank: false,
+        logic: false,
+    };
+
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift: true,
+        memory_increment_by_x: false,
+        memory_leave_i_unchanged: true,
+        wrap: false,
+        jump: true,
+        vblank: false,
+        logic: false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_mut() {
+        let mut quirks = Quirks::VIP;
+        for name in Quirks::NAMES {
+            *quirks.get_mut(name).unwrap() = true;
+        }
+        assert!(quirks.memory_increment_by_x && quirks.memory_leave_i_unchanged);
+        assert_eq!(quirks.get_mut("memoryIncrementByX"), None);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::display::Display;
+use crate::palette::Palette;
+use alloc::vec::Vec;
+
+/// Pixel-art upscalers that can be applied before converting to RGBA.
+///
+/// Only scalers that pick from existing pixels are offered, since frames are
+/// scaled before they are colored. Blending ones like hq2x aren't supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scaler {
+    /// Repeat each pixel `n` times in both directions.
+    Nearest(usize),
+    /// Scale2x (AdvMAME2x) edge smoothing. `parse` also accepts `epx`, the
+    /// algorithm Scale2x was derived from, as an alias since both give the
+    /// same output.
+    Scale2x,
+    /// Scale3x (AdvMAME3x) edge smoothing.
+    Scale3x,
+}
+
+impl Scaler {
+    pub fn factor(&self) -> usize {
+        match self {
+            Scaler::Nearest(n) => (*n).max(1),
+            Scaler::Scale2x => 2,
+            Scaler::Scale3x => 3,
+        }
+    }
+
+    /// Parse `nearest[:n]`, `scale2x` (or its alias `epx`) or `scale3x`.
+    pub fn parse(s: &str) -> Option<Scaler> {
+        match s {
+            "nearest" => Some(Scaler::Nearest(1)),
+            "scale2x" | "epx" => Some(Scaler::Scale2x),
+            "scale3x" => Some(Scaler::Scale3x),
+            _ => s
+                .strip_prefix("nearest:")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(Scaler::Nearest),
+        }
+    }
+}
+
+impl Default for Scaler {
+    fn default() -> Self {
+        Scaler::Nearest(1)
+    }
+}
+
+/// Scale a row-major `width` x `height` buffer, returning the scaled buffer.
+///
+/// Works on any pixel type so that plain on/off frames and persistence
+/// intensities can both be scaled before they are colored.
+///
+/// Panics if `src` doesn't hold `width * height` pixels.
+pub fn scale<T: Copy + PartialEq>(
+    src: &[T],
+    width: usize,
+    height: usize,
+    scaler: Scaler,
+) -> Vec<T> {
+    assert_eq!(
+        src.len(),
+        width * height,
+        "scale: expected a {}x{} buffer",
+        width,
+        height
+    );
+    if src.is_empty() {
+        return Vec::new();
+    }
+    let factor = scaler.factor();
+    let out_width = width * factor;
+    let mut out = Vec::with_capacity(src.len() * factor * factor);
+    out.resize(src.len() * factor * factor, src[0]);
+
+    // Neighbours are clamped at the edges.
+    let at = |x: isize, y: isize| -> T {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        src[y * width + x]
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let e = at(xi, yi);
+            let block: [T; 9] = match scaler {
+                Scaler::Nearest(_) => [e; 9],
+                Scaler::Scale2x => {
+                    let (b, d, f, h) = (
+                        at(xi, yi - 1),
+                        at(xi - 1, yi),
+                        at(xi + 1, yi),
+                        at(xi, yi + 1),
+                    );
+                    if b != h && d != f {
+                        [
+                            if d == b { d } else { e },
+                            if b == f { f } else { e },
+                            if d == h { d } else { e },
+                            if h == f { f } else { e },
+                            e,
+                            e,
+                            e,
+                            e,
+                            e,
+                        ]
+                    } else {
+                        [e; 9]
+                    }
+                }
+                Scaler::Scale3x => {
+                    let (a, b, c) = (at(xi - 1, yi - 1), at(xi, yi - 1), at(xi + 1, yi - 1));
+                    let (d, f) = (at(xi - 1, yi), at(xi + 1, yi));
+                    let (g, h, i) = (at(xi - 1, yi + 1), at(xi, yi + 1), at(xi + 1, yi + 1));
+                    if b != h && d != f {
+                        [
+                            if d == b { d } else { e },
+                            if (d == b && e != c) || (b == f && e != a) {
+                                b
+                            } else {
+                                e
+                            },
+                            if b == f { f } else { e },
+                            if (d == b && e != g) || (d == h && e != a) {
+                                d
+                            } else {
+                                e
+                            },
+                            e,
+                            if (b == f && e != i) || (h == f && e != c) {
+                                f
+                            } else {
+                                e
+                            },
+                            if d == h { d } else { e },
+                            if (d == h && e != i) || (h == f && e != g) {
+                                h
+                            } else {
+                                e
+                            },
+                            if h == f { f } else { e },
+                        ]
+                    } else {
+                        [e; 9]
+                    }
+                }
+            };
+
+            for dy in 0..factor {
+                for dx in 0..factor {
+                    let value = match scaler {
+                        Scaler::Nearest(_) => e,
+                        _ => block[dy * factor + dx],
+                    };
+                    out[(y * factor + dy) * out_width + x * factor + dx] = value;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Turns the display's frame buffer into RGBA, shared by every frontend.
+pub struct Renderer {
+    pub palette: Palette,
+    pub scaler: Scaler,
+    rgba: Vec<u8>,
+}
+
+impl Renderer {
+    pub fn new(palette: Palette, scaler: Scaler) -> Renderer {
+        Renderer {
+            palette,
+            scaler,
+            rgba: Vec::new(),
+        }
+    }
+
+    /// Width and height of the RGBA output in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        let factor = self.scaler.factor();
+        (Display::WIDTH * factor, Display::HEIGHT * factor)
+    }
+
+    /// Render an on/off frame buffer as returned by `Chip8VM::get_frame_buffer`.
+    pub fn render(&mut self, frame: &[bool]) -> &[u8] {
+        let scaled = self.scale(frame);
+        let palette = self.palette;
+        self.fill(scaled.iter().map(|p| palette.color(*p as u8)))
+    }
+
+    /// Render per-pixel intensities, such as the output of `Persistence::apply`.
+    pub fn render_intensity(&mut self, intensity: &[u8]) -> &[u8] {
+        let scaled = self.scale(intensity);
+        let palette = self.palette;
+        self.fill(scaled.iter().map(|i| palette.shade(*i)))
+    }
+
+    fn scale<T: Copy + PartialEq>(&self, src: &[T]) -> Vec<T> {
+        scale(src, Display::WIDTH, Display::HEIGHT, self.scaler)
+    }
+
+    fn fill(&mut self, colors: impl Iterator<Item = [u8; 4]>) -> &[u8] {
+        self.rgba.clear();
+        for rgba in colors {
+            self.rgba.extend_from_slice(&rgba);
+        }
+        &self.rgba
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Scaler::parse("nearest"), Some(Scaler::Nearest(1)));
+        assert_eq!(Scaler::parse("nearest:4"), Some(Scaler::Nearest(4)));
+        assert_eq!(Scaler::parse("nearest:0"), None);
+        assert_eq!(Scaler::parse("scale3x"), Some(Scaler::Scale3x));
+        assert_eq!(Scaler::parse("epx"), Some(Scaler::Scale2x));
+        assert_eq!(Scaler::parse("hq2x"), None);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let out = scale(&[1, 2, 3, 4], 2, 2, Scaler::Nearest(2));
+        assert_eq!(out, vec![1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+    }
+
+    #[test]
+    fn test_scale_empty() {
+        assert!(scale::<u8>(&[], 0, 0, Scaler::Scale3x).is_empty());
+        assert!(scale::<u8>(&[], 64, 0, Scaler::Nearest(2)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected a 3x2 buffer")]
+    fn test_scale_size_mismatch() {
+        scale(&[1, 2, 3, 4], 3, 2, Scaler::Scale2x);
+    }
+
+    #[test]
+    fn test_scale2x_smooths_diagonal() {
+        // A diagonal line is smoothed into a continuous stroke.
+        #[rustfmt::skip]
+        let src = [
+            1, 0, 0,
+            0, 1, 0,
+            0, 0, 1,
+        ];
+        let out = scale(&src, 3, 3, Scaler::Scale2x);
+        #[rustfmt::skip]
+        let expected = vec![
+            1, 1, 0, 0, 0, 0,
+            1, 0, 1, 0, 0, 0,
+            0, 1, 1, 1, 0, 0,
+            0, 0, 1, 1, 1, 0,
+            0, 0, 0, 1, 0, 1,
+            0, 0, 0, 0, 1, 1,
+        ];
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_scale3x_flat_areas_unchanged() {
+        let out = scale(&[7; 4], 2, 2, Scaler::Scale3x);
+        assert_eq!(out, vec![7; 36]);
+    }
+
+    #[test]
+    fn test_renderer() {
+        let mut frame = [false; Display::WIDTH * Display::HEIGHT];
+        frame[1] = true;
+        let mut renderer = Renderer::new(Palette::HIGH_CONTRAST, Scaler::Nearest(2));
+        assert_eq!(renderer.size(), (128, 64));
+        let rgba = renderer.render(&frame);
+        assert_eq!(rgba.len(), 128 * 64 * 4);
+        assert_eq!(&rgba[0..4], &Palette::HIGH_CONTRAST.background());
+        assert_eq!(&rgba[8..12], &Palette::HIGH_CONTRAST.foreground());
+        assert_eq!(
+            &rgba[128 * 4 + 8..128 * 4 + 12],
+            &Palette::HIGH_CONTRAST.foreground()
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use core::fmt;
+use sha1::{Digest, Sha1};
+
+/// Details about a loaded ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RomInfo {
+    /// Size in bytes.
+    pub size: usize,
+    /// SHA-1 of the ROM image, the key used by ROM databases.
+    pub sha1: [u8; 20],
+}
+
+impl RomInfo {
+    pub fn new(rom: &[u8]) -> RomInfo {
+        RomInfo {
+            size: rom.len(),
+            sha1: Sha1::digest(rom).into(),
+        }
+    }
+
+    /// The hash as 40 lowercase hex digits.
+    pub fn sha1_hex(&self) -> impl fmt::Display + '_ {
+        Hex(&self.sha1)
+    }
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rom_info() {
+        let info = RomInfo::new(b"abc");
+        assert_eq!(info.size, 3);
+        assert_eq!(
+            info.sha1_hex().to_string(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+}
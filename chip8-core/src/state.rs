@@ -0,0 +1,112 @@
+//! Binary save-state encoding shared by the VM's components.
+//!
+//! The format is a small header (magic + version) followed by each component
+//! writing its fields in a fixed order. Integers are big-endian.
+
+use crate::vm::VMError;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+const MAGIC: &[u8; 4] = b"C8SS";
+const VERSION: u8 = 1;
+
+#[cfg(feature = "alloc")]
+pub(crate) struct StateWriter {
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl StateWriter {
+    pub(crate) fn new() -> StateWriter {
+        let mut buf = Vec::with_capacity(4 * 1024 + 512);
+        buf.extend_from_slice(MAGIC);
+        buf.push(VERSION);
+        StateWriter { buf }
+    }
+
+    pub(crate) fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub(crate) fn u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&val.to_be_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, val: &[u8]) {
+        self.buf.extend_from_slice(val);
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Result<StateReader<'a>, VMError> {
+        let mut r = StateReader { data };
+        if r.bytes(MAGIC.len())? != MAGIC || r.u8()? != VERSION {
+            return Err(VMError::InvalidSaveState());
+        }
+        Ok(r)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, VMError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, VMError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], VMError> {
+        if self.data.len() < len {
+            return Err(VMError::InvalidSaveState());
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    /// Fails unless every byte of the state was consumed.
+    pub(crate) fn finish(self) -> Result<(), VMError> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(VMError::InvalidSaveState())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut w = StateWriter::new();
+        w.u8(7);
+        w.u16(0x1234);
+        w.bytes(&[1, 2, 3]);
+        let data = w.finish();
+
+        let mut r = StateReader::new(&data).unwrap();
+        assert_eq!(r.u8().unwrap(), 7);
+        assert_eq!(r.u16().unwrap(), 0x1234);
+        assert_eq!(r.bytes(3).unwrap(), &[1, 2, 3]);
+        assert!(r.finish().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(StateReader::new(b"nope!").is_err());
+        assert!(StateReader::new(b"C8").is_err());
+        let empty = StateWriter::new().finish();
+        let mut r = StateReader::new(&empty).unwrap();
+        assert!(r.u16().is_err());
+    }
+}
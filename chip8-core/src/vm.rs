@@ -1,18 +1,41 @@
+use core::fmt;
+use core::ops::{Index, IndexMut};
 use log::debug;
-use rand::Rng;
-use std::fs;
-use std::ops::{Index, IndexMut};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
 use thiserror::Error;
 
 use crate::display::Display;
+use crate::events::{Event, EventQueue, Rect};
+use crate::fault::FaultPolicy;
+use crate::font::Font;
 use crate::instructions::Instruction;
 use crate::keypad::{Key, KeyState, KeyWait, Keypad};
-use crate::memory::{Memory, Stack};
+use crate::memory::{CallStack, Memory, Stack, StackMode, RAM_SIZE};
+use crate::platform::Platform;
+use crate::quirks::Quirks;
+use crate::rom::RomInfo;
+use crate::state::StateReader;
+#[cfg(feature = "alloc")]
+use crate::state::StateWriter;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
 
 const NUM_REGISTERS: usize = 16;
-const ROM_START: usize = 0x200;
+// `Display` is shadowed by the DXYN instruction inside `execute`.
+const SCREEN_WIDTH: usize = Display::WIDTH;
+const SCREEN_HEIGHT: usize = Display::HEIGHT;
+/// Where the font goes unless `set_font_address` moves it.
+pub const DEFAULT_FONT_ADDRESS: u16 = 0x050;
+/// Instructions per 60Hz frame for frontends that run whole frames, 480 per
+/// second. The desktop frontend isn't tied to frames and runs at 500Hz.
+pub const DEFAULT_CYCLES_PER_FRAME: u32 = 8;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum VMError {
     #[error("Unknown instruction: {0}")]
     UnknownInstruction(u16),
@@ -20,14 +43,76 @@ pub enum VMError {
     #[error("Unknown key: {0}")]
     UnknownKey(u8),
 
+    #[error("Invalid action: {0}")]
+    InvalidAction(usize),
+
+    #[error("Memory value at {addr:#X} is {len} bytes, too long to read")]
+    InvalidMemoryValue { addr: u16, len: u8 },
+
     #[error("Rom load error: {0}")]
-    RomLoadFailure(String),
+    RomLoadFailure(RomLoadError),
+
+    #[error("ROM is empty")]
+    RomEmpty(),
+
+    #[error("ROM is {size} bytes, only {max} fit in memory")]
+    RomTooLarge { size: usize, max: usize },
 
     #[error("Stack underflow")]
     StackUnderflow(),
 
     #[error("Stack overflow")]
     StackOverflow(),
+
+    #[error("Invalid save state")]
+    InvalidSaveState(),
+
+    #[error("Font at {0:#X} doesn't fit in memory")]
+    InvalidFontAddress(u16),
+
+    #[error("The platform and font can't change once a ROM is loaded")]
+    RomAlreadyLoaded(),
+
+    #[error("Memory access out of bounds at {addr:#X} (PC {pc:#X})")]
+    MemoryOutOfBounds { addr: usize, pc: u16 },
+}
+
+/// Why a ROM couldn't be read. Keeps the I/O error's kind and message, the
+/// latter prefixed with the path, where the features allow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomLoadError {
+    #[cfg(feature = "std")]
+    kind: std::io::ErrorKind,
+    #[cfg(feature = "alloc")]
+    message: String,
+}
+
+impl RomLoadError {
+    /// Wrap `err`, with `context` (usually the path) in front of its message.
+    #[cfg(feature = "std")]
+    pub fn io(context: impl fmt::Display, err: &std::io::Error) -> RomLoadError {
+        RomLoadError {
+            kind: err.kind(),
+            message: format!("{}: {}", context, err),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for RomLoadError {
+    #[cfg(feature = "alloc")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("couldn't read the ROM")
+    }
 }
 
 struct Registers {
@@ -38,11 +123,11 @@ struct Registers {
 type RegNum = u8;
 
 impl Registers {
-    fn new() -> Registers {
-        return Registers {
+    fn new(pc: u16) -> Registers {
+        Registers {
             data: [0; NUM_REGISTERS],
-            pc: ROM_START,
-        };
+            pc: pc as usize,
+        }
     }
 }
 
@@ -59,7 +144,9 @@ impl IndexMut<RegNum> for Registers {
     }
 }
 
-pub struct Chip8VM {
+/// The VM, generic over the random number generator used by CXNN so targets
+/// without an entropy source can bring their own.
+pub struct Chip8VM<R: RngCore = StdRng> {
     memory: Memory,
     display: Display,
     registers: Registers,
@@ -68,16 +155,88 @@ pub struct Chip8VM {
     index_register: usize,
     delay_timer: u8,
     sound_timer: u8,
+    platform: Platform,
+    quirks: Quirks,
+    // set by DXYN with the vblank quirk, cleared by tick_timers.
+    waiting_for_vblank: bool,
+    fault_policy: FaultPolicy,
+    font: Font,
+    font_address: u16,
+    // set once memory holds a program, after which the font can't be written.
+    rom_loaded: bool,
+    events: EventQueue,
+    // address of the self-jump last reported as Halted, so it's only
+    // reported once. Cleared when anything else runs.
+    halted: Option<u16>,
+    // address of the instruction being executed, for fault reports.
+    current_pc: usize,
+    // instructions run since the VM was created, excluding faults and waits.
+    instructions: u64,
+    rng: R,
+}
+
+// VMs hold no shared or global state, so batch runners can move them across
+// threads. Fails to compile if a field stops being Send.
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Chip8VM>();
+};
+
+#[cfg(feature = "std")]
+impl Default for Chip8VM {
+    fn default() -> Self {
+        Chip8VM::new()
+    }
 }
 
+#[cfg(feature = "std")]
 impl Chip8VM {
+    /// A VM whose random number generator is seeded from the OS.
     pub fn new() -> Chip8VM {
-        Chip8VM {
+        Chip8VM::with_rng(StdRng::from_entropy())
+    }
+
+    pub fn load_rom(&mut self, rom_path: &String) -> Result<RomInfo, VMError> {
+        let load_error = |e| VMError::RomLoadFailure(RomLoadError::io(rom_path, &e));
+        let file = std::fs::File::open(rom_path).map_err(load_error)?;
+        let rom_bytes = self.read_rom(file).map_err(load_error)?;
+        let info = self.load_rom_bytes(&rom_bytes)?;
+        debug!("loaded {} into vm memory", rom_path);
+        Ok(info)
+    }
+
+    /// Load a ROM from any reader, e.g. an archive entry or a network stream.
+    /// Reading stops as soon as the ROM is known to be too large.
+    pub fn load_rom_reader(&mut self, reader: impl Read) -> Result<RomInfo, VMError> {
+        let rom_bytes = self
+            .read_rom(reader)
+            .map_err(|e| VMError::RomLoadFailure(RomLoadError::io("reading ROM", &e)))?;
+        self.load_rom_bytes(&rom_bytes)
+    }
+
+    fn read_rom(&self, reader: impl Read) -> std::io::Result<Vec<u8>> {
+        let max = self.platform.max_rom_size();
+        let mut rom_bytes = Vec::with_capacity(max);
+        reader.take(max as u64 + 1).read_to_end(&mut rom_bytes)?;
+        Ok(rom_bytes)
+    }
+}
+
+impl<R: RngCore + SeedableRng> Chip8VM<R> {
+    /// Reseed the random number generator used by CXNN so runs are repeatable.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = R::seed_from_u64(seed);
+    }
+}
+
+impl<R: RngCore> Chip8VM<R> {
+    pub fn with_rng(rng: R) -> Chip8VM<R> {
+        let mut vm = Chip8VM {
             memory: Memory::new(),
             display: Display::new(),
-            registers: Registers::new(),
+            registers: Registers::new(Platform::default().load_address()),
             // stack manages addresses for subroutines
-            stack: Stack::default(),
+            stack: Stack::new(Platform::default().stack_depth(), StackMode::Internal),
             // keypad tracks state of pressed keys for GetKey, Skip*Pressed instructions.
             // clients must call handle_key when keys are pressed/released.
             keypad: Keypad::new(),
@@ -87,32 +246,254 @@ impl Chip8VM {
             // clients should call tick_timers for this decrement at 60hz
             delay_timer: 0,
             sound_timer: 0,
+            platform: Platform::default(),
+            quirks: Quirks::default(),
+            waiting_for_vblank: false,
+            fault_policy: FaultPolicy::default(),
+            // hex digit sprites for FX29, written into memory below.
+            font: Platform::default().font(),
+            font_address: DEFAULT_FONT_ADDRESS,
+            rom_loaded: false,
+            // what happened since the frontend last asked, see poll_event.
+            events: EventQueue::new(),
+            halted: None,
+            current_pc: 0,
+            instructions: 0,
+            // source for CXNN, reseed with seed_rng for reproducible runs.
+            rng,
+        };
+        vm.write_font();
+        vm
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// Switch platform, move PC to its load address and use its font and
+    /// stack depth. Fails once a ROM is loaded. Quirks are left alone, pass
+    /// `platform.quirks()` to `set_quirks` to match the platform's behavior too.
+    pub fn set_platform(&mut self, platform: Platform) -> Result<(), VMError> {
+        self.set_font(platform.font())?;
+        self.platform = platform;
+        self.registers.pc = platform.load_address() as usize;
+        self.set_max_stack_depth(platform.stack_depth());
+        Ok(())
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn fault_policy(&self) -> FaultPolicy {
+        self.fault_policy
+    }
+
+    /// Choose how accesses past the end of memory are handled.
+    pub fn set_fault_policy(&mut self, policy: FaultPolicy) {
+        self.fault_policy = policy;
+    }
+
+    pub fn max_stack_depth(&self) -> usize {
+        self.stack.max_depth()
+    }
+
+    /// Limit nested calls, up to `MAX_STACK_DEPTH`. Empties the stack.
+    pub fn set_max_stack_depth(&mut self, depth: usize) {
+        self.stack = Stack::new(depth, self.stack.mode());
+    }
+
+    pub fn stack_mode(&self) -> StackMode {
+        self.stack.mode()
+    }
+
+    /// Keep return addresses inside the VM or in RAM. Empties the stack.
+    pub fn set_stack_mode(&mut self, mode: StackMode) {
+        self.stack = Stack::new(self.stack.max_depth(), mode);
+    }
+
+    pub fn font(&self) -> Font {
+        self.font
+    }
+
+    /// Replace the hex digit sprites, e.g. with a built-in `Font` or a custom
+    /// one. The font is written straight to memory, so this fails once a ROM
+    /// is loaded rather than overwrite it.
+    pub fn set_font(&mut self, font: Font) -> Result<(), VMError> {
+        if self.rom_loaded {
+            return Err(VMError::RomAlreadyLoaded());
         }
+        self.font = font;
+        self.write_font();
+        Ok(())
     }
 
-    pub fn load_rom(&mut self, rom_path: &String) -> Result<(), VMError> {
-        match fs::read(rom_path) {
-            Ok(rom_bytes) => {
-                for (i, b) in rom_bytes.iter().enumerate() {
-                    self.memory.write(ROM_START + i, *b);
-                }
-                debug!("loaded {} into vm memory", rom_path);
-                Ok(())
-            }
-            Err(e) => Err(VMError::RomLoadFailure(e.to_string())),
+    pub fn font_address(&self) -> u16 {
+        self.font_address
+    }
+
+    /// Move the font, for ROMs that expect it somewhere other than
+    /// `DEFAULT_FONT_ADDRESS`. The old location is zeroed. Fails once a ROM
+    /// is loaded.
+    pub fn set_font_address(&mut self, address: u16) -> Result<(), VMError> {
+        if self.rom_loaded {
+            return Err(VMError::RomAlreadyLoaded());
         }
+        if address as usize + Font::SIZE > RAM_SIZE {
+            return Err(VMError::InvalidFontAddress(address));
+        }
+        let old = self.font_address as usize;
+        for addr in old..old + Font::SIZE {
+            self.memory.write(addr, 0);
+        }
+        self.font_address = address;
+        self.write_font();
+        Ok(())
+    }
+
+    fn write_font(&mut self) {
+        let start = self.font_address as usize;
+        for (i, b) in self.font.bytes().enumerate() {
+            self.memory.write(start + i, b);
+        }
+    }
+
+    /// Load a ROM that's already in memory, e.g. fetched by a browser. Memory
+    /// is left untouched if the ROM doesn't fit the platform.
+    pub fn load_rom_bytes(&mut self, rom_bytes: &[u8]) -> Result<RomInfo, VMError> {
+        let max = self.platform.max_rom_size();
+        if rom_bytes.is_empty() {
+            return Err(VMError::RomEmpty());
+        }
+        if rom_bytes.len() > max {
+            return Err(VMError::RomTooLarge {
+                size: rom_bytes.len(),
+                max,
+            });
+        }
+        let start = self.platform.load_address() as usize;
+        for (i, b) in rom_bytes.iter().enumerate() {
+            self.memory.write(start + i, *b);
+        }
+        self.rom_loaded = true;
+        Ok(RomInfo::new(rom_bytes))
+    }
+
+    /// Snapshot the full machine state so it can be restored with `load_state`.
+    /// The CXNN random number generator is not included.
+    #[cfg(feature = "alloc")]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        self.memory.save(&mut w);
+        self.display.save(&mut w);
+        w.bytes(&self.registers.data);
+        w.u16(self.registers.pc as u16);
+        w.u16(self.index_register as u16);
+        w.u8(self.delay_timer);
+        w.u8(self.sound_timer);
+        self.stack.save(&mut w, &self.memory);
+        self.keypad.save(&mut w);
+        w.finish()
+    }
+
+    /// Restore a snapshot from `save_state`. The VM is left untouched on error.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), VMError> {
+        let mut r = StateReader::new(state)?;
+        let mut memory = Memory::new();
+        memory.load(&mut r)?;
+        let mut display = Display::new();
+        display.load(&mut r)?;
+        let mut registers = Registers::new(self.platform.load_address());
+        registers.data.copy_from_slice(r.bytes(NUM_REGISTERS)?);
+        // PC can be past the end after a skip, the next fetch applies the fault policy.
+        registers.pc = r.u16()? as usize;
+        let index_register = r.u16()? as usize;
+        let delay_timer = r.u8()?;
+        let sound_timer = r.u8()?;
+        let mut stack = Stack::new(self.stack.max_depth(), self.stack.mode());
+        stack.load(&mut r)?;
+        let mut keypad = Keypad::new();
+        keypad.load(&mut r)?;
+        r.finish()?;
+
+        self.memory = memory;
+        self.display = display;
+        self.registers = registers;
+        self.index_register = index_register;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.stack = stack;
+        self.keypad = keypad;
+        self.halted = None;
+        self.rom_loaded = true;
+        Ok(())
+    }
+
+    /// Soft reset, like the machine's reset switch: registers, timers, the
+    /// stack, the screen and the keypad are cleared and PC goes back to the
+    /// load address. Memory is left alone, so the ROM doesn't need reloading.
+    pub fn reset(&mut self) {
+        if self.sound_timer > 0 {
+            self.events.push(Event::SoundStopped);
+        }
+        if self.display.rows().iter().any(|row| *row != 0) {
+            self.display.clear();
+            self.events.push(Event::DisplayCleared);
+        }
+        self.registers = Registers::new(self.platform.load_address());
+        self.index_register = 0;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.stack = Stack::new(self.stack.max_depth(), self.stack.mode());
+        self.keypad = Keypad::new();
+        self.waiting_for_vblank = false;
+        self.halted = None;
     }
 
     pub fn cycle(&mut self) -> Result<(), VMError> {
         // When we're waiting on a key we won't execute any more instructions
         // until handle_key is called and `key_wait` gets reset.
-        if self.keypad.is_waiting() {
+        if self.keypad.is_waiting() || self.waiting_for_vblank {
             return Ok(());
         }
+        let result = self.step();
+        match &result {
+            Ok(()) => self.instructions += 1,
+            Err(err) => self.events.push(Event::Fault(err.clone())),
+        }
+        result
+    }
+
+    /// Next thing the VM reported, oldest first. Only the latest
+    /// `EVENT_CAPACITY` events are kept, so drain them at least once a frame.
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop()
+    }
+
+    /// Events lost because nobody polled them in time, since the last call.
+    pub fn dropped_events(&mut self) -> usize {
+        self.events.take_dropped()
+    }
+
+    fn step(&mut self) -> Result<(), VMError> {
+        // Skips can carry PC past the end of memory.
+        let pc = self.registers.pc;
+        self.current_pc = pc;
+        if self.fault_policy == FaultPolicy::Fault && pc + 1 >= RAM_SIZE {
+            return Err(VMError::MemoryOutOfBounds {
+                addr: pc.max(RAM_SIZE),
+                pc: pc as u16,
+            });
+        }
+        self.registers.pc = pc & 0xFFF;
 
         // need to read 2 bytes for the full instruction.
         let op1 = self.memory.read(self.registers.pc);
-        let op2 = self.memory.read(self.registers.pc + 1);
+        let op2 = self.memory.read((self.registers.pc + 1) & 0xFFF);
         debug!("execute instruction @ {:#X}", self.registers.pc);
 
         // combine to hex operation
@@ -120,6 +501,9 @@ impl Chip8VM {
         self.registers.pc += 2;
 
         let instr = Instruction::decode(op);
+        if !matches!(instr, Instruction::Jump(addr) if addr as usize == pc) {
+            self.halted = None;
+        }
         self.execute(instr)
     }
 
@@ -127,7 +511,97 @@ impl Chip8VM {
         self.display.get_frame_buffer()
     }
 
+    /// The screen, for change tracking and the frame hash.
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// Mark the current frame as presented, see `Display::clear_dirty`.
+    pub fn clear_display_dirty(&mut self) {
+        self.display.clear_dirty();
+    }
+
+    /// General purpose registers V0 through VF.
+    pub fn registers(&self) -> &[u8; NUM_REGISTERS] {
+        &self.registers.data
+    }
+
+    /// Overwrite VX, e.g. from a debugger. `reg` is masked to 0-F.
+    pub fn set_register(&mut self, reg: u8, val: u8) {
+        self.registers.data[reg as usize & 0xF] = val;
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.registers.pc as u16
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.registers.pc = pc as usize & 0xFFF;
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register as u16
+    }
+
+    pub fn set_index_register(&mut self, val: u16) {
+        self.index_register = val as usize;
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn set_delay_timer(&mut self, val: u8) {
+        self.delay_timer = val;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn set_sound_timer(&mut self, val: u8) {
+        self.sound_timer = val;
+    }
+
+    /// Return addresses of active subroutine calls, oldest first.
+    pub fn call_stack(&self) -> CallStack {
+        self.stack.call_stack(&self.memory)
+    }
+
+    /// Instructions executed since the VM was created. Unlike a count of
+    /// `cycle` calls, this leaves out cycles spent waiting for a key or the
+    /// vblank, and instructions that faulted.
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Whether the VM is blocked on FX0A waiting for a key.
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.keypad.is_waiting()
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        self.memory.as_slice()
+    }
+
+    /// Overwrite a byte of RAM. `addr` wraps to 12 bits.
+    pub fn poke(&mut self, addr: u16, val: u8) {
+        self.memory.write(addr as usize & 0xFFF, val);
+    }
+
+    /// Decode the instruction stored at `addr` without executing it.
+    pub fn instruction_at(&self, addr: u16) -> Instruction {
+        let addr = addr as usize & 0xFFF;
+        let op1 = self.memory.read(addr);
+        let op2 = self.memory.read((addr + 1) & 0xFFF);
+        Instruction::decode(((op1 as u16) << 8) | op2 as u16)
+    }
+
     pub fn tick_timers(&mut self) {
+        self.waiting_for_vblank = false;
+        if self.sound_timer == 1 {
+            self.events.push(Event::SoundStopped);
+        }
         self.delay_timer = if self.delay_timer == 0 {
             0
         } else {
@@ -153,11 +627,10 @@ impl Chip8VM {
                     self.registers[vx] = key_code;
                     self.keypad.set_wait(KeyWait::WaitingForRelease(key_code));
                 }
-                KeyWait::WaitingForRelease(wait_key_code) => {
-                    if key_code == wait_key_code {
-                        self.keypad.set_wait(KeyWait::NotWaiting);
-                        self.registers.pc += 2;
-                    }
+                KeyWait::WaitingForRelease(wait_key_code) if key_code == wait_key_code => {
+                    self.keypad.set_wait(KeyWait::NotWaiting);
+                    self.registers.pc += 2;
+                    self.events.push(Event::KeyWaitEnded { key: key_code });
                 }
                 _ => {}
             }
@@ -174,21 +647,29 @@ impl Chip8VM {
             ClearScreen => {
                 debug!("Executing ClearScreen");
                 self.display.clear();
+                self.events.push(Event::DisplayCleared);
             }
             ExitSubroutine => {
                 debug!("Exit subroutine");
-                if let Ok(addr) = self.stack.pop() {
-                    self.registers.pc = addr as usize;
-                }
+                let addr = self.stack.pop(&self.memory)?;
+                self.registers.pc = addr as usize;
+                self.events.push(Event::SubroutineReturned { addr });
             }
             Jump(addr) => {
                 debug!("Jumping to address {:#X}", addr);
+                if addr as usize == self.current_pc && self.halted != Some(addr) {
+                    self.events.push(Event::Halted { pc: addr });
+                    self.halted = Some(addr);
+                }
                 self.registers.pc = addr as usize;
             }
             CallSubroutine(addr) => {
                 debug!("Calling subroutine at address {:#X}", addr);
-                self.stack.push(self.registers.pc as u16)?;
+                let return_addr = self.registers.pc as u16;
+                self.stack.push(&mut self.memory, return_addr)?;
                 self.registers.pc = addr as usize;
+                self.events
+                    .push(Event::SubroutineCalled { addr, return_addr });
             }
             SkipValEqual(vx, val) => {
                 debug!("Skipping if register {} equals value {:#X}", vx, val);
@@ -223,14 +704,23 @@ impl Chip8VM {
             OR(vx, vy) => {
                 debug!("ORing register {} with register {}", vx, vy);
                 self.registers[vx] |= self.registers[vy];
+                if self.quirks.logic {
+                    self.registers[0xF] = 0;
+                }
             }
             AND(vx, vy) => {
                 debug!("ANDing register {} with register {}", vx, vy);
                 self.registers[vx] &= self.registers[vy];
+                if self.quirks.logic {
+                    self.registers[0xF] = 0;
+                }
             }
             XOR(vx, vy) => {
                 debug!("XORing register {} with register {}", vx, vy);
                 self.registers[vx] ^= self.registers[vy];
+                if self.quirks.logic {
+                    self.registers[0xF] = 0;
+                }
             }
             Add(vx, vy) => {
                 debug!("Adding register {} to register {}", vy, vx);
@@ -259,15 +749,15 @@ impl Chip8VM {
                 // Set carry flag for underflow
                 self.registers[0xF] = if vy_val >= vx_val { 1 } else { 0 };
             }
-            ShiftRight(vx, _) => {
+            ShiftRight(vx, vy) => {
                 debug!("Shifting register {} right", vx);
-                let reg_val = self.registers[vx];
+                let reg_val = self.registers[if self.quirks.shift { vx } else { vy }];
                 self.registers[vx] = reg_val >> 1;
                 self.registers[0xF] = reg_val & 1;
             }
-            ShiftLeft(vx, _) => {
+            ShiftLeft(vx, vy) => {
                 debug!("Shifting register {} left", vx);
-                let reg_val = self.registers[vx];
+                let reg_val = self.registers[if self.quirks.shift { vx } else { vy }];
                 self.registers[vx] = reg_val << 1;
                 self.registers[0xF] = (reg_val >> 7) & 1;
             }
@@ -285,20 +775,27 @@ impl Chip8VM {
             }
             JumpOffset(val) => {
                 debug!("Jumping to address with offset {:#X}", val);
-                self.registers.pc = (self.registers[0x0] as usize + val as usize) & 0xFFF;
+                // With the jump quirk BXNN uses VX, X being the high nibble of the address.
+                let offset_reg = if self.quirks.jump {
+                    (val >> 8) as u8
+                } else {
+                    0
+                };
+                self.registers.pc = (self.registers[offset_reg] as usize + val as usize) & 0xFFF;
             }
             Random(vx, val) => {
                 debug!(
                     "Generating random number for register {} with mask {:#X}",
                     vx, val
                 );
-                let rand_val = rand::thread_rng().gen_range(0..=255) as u8;
+                let rand_val = self.rng.next_u32() as u8;
                 self.registers[vx] = rand_val & val;
             }
             Display(vx, vy, height) => {
-                // Wrap coordinates around display.
-                let x_coord = self.registers[vx];
-                let y_coord = self.registers[vy];
+                // The starting position always wraps, the sprite itself
+                // wraps or clips at the edges depending on quirks.
+                let x_coord = self.registers[vx] as usize % SCREEN_WIDTH;
+                let y_coord = self.registers[vy] as usize % SCREEN_HEIGHT;
                 debug!(
                     "Displaying sprite at ({}, {}) with height {}",
                     x_coord, y_coord, height
@@ -306,29 +803,26 @@ impl Chip8VM {
 
                 // VF starts at 0, will flip if any pixels are turned off.
                 let mut vf = 0;
-                let mut ireg: usize = self.index_register;
-
-                for row in 0..height {
-                    let sprite_byte: u8 = self.memory.read(ireg as usize);
-                    let mut x_offset = 0;
-                    for bit in (0..8).rev() {
-                        let b: u8 = sprite_byte >> bit & 1;
-                        let x = (x_coord as usize + x_offset) as usize;
-                        let y = (y_coord + row) as usize;
-                        if b == 1 {
-                            let current_pixel = self.display.get(x, y).unwrap_or(false);
-                            // chip-8 uses XOR logic for setting pixels
-                            let new_pixel = current_pixel ^ true;
-                            if current_pixel && !new_pixel {
-                                vf = 1;
-                            }
-                            self.display.set(x, y, new_pixel);
-                        }
-                        x_offset += 1;
+
+                for row in 0..height as usize {
+                    let y = y_coord + row;
+                    // Clipped rows aren't read, so they can't fault either.
+                    if y >= SCREEN_HEIGHT && !self.quirks.wrap {
+                        break;
+                    }
+                    let sprite_byte = self.read_memory(self.index_register + row)?;
+                    // chip-8 XORs sprites onto the screen.
+                    if self
+                        .display
+                        .draw_sprite_row(x_coord, y, sprite_byte, self.quirks.wrap)
+                    {
+                        vf = 1;
                     }
-                    ireg += 1;
                 }
                 self.registers[0xF] = vf;
+                self.waiting_for_vblank = self.quirks.vblank;
+                let rect = self.sprite_rect(x_coord, y_coord, height as usize);
+                self.events.push(Event::DisplayDrawn(rect));
             }
             SkipIfPressed(vx) => {
                 debug!("Skipping if key in register {} is pressed", vx);
@@ -356,30 +850,40 @@ impl Chip8VM {
             }
             SetSoundTimer(vx) => {
                 debug!("Setting sound timer to value in register {}", vx);
+                let was_playing = self.sound_timer > 0;
                 self.sound_timer = self.registers[vx];
+                match (was_playing, self.sound_timer > 0) {
+                    (false, true) => self.events.push(Event::SoundStarted),
+                    (true, false) => self.events.push(Event::SoundStopped),
+                    _ => {}
+                }
             }
             AddToIndex(vx) => {
                 debug!("Adding register {} to index register", vx);
-                self.index_register += self.registers[vx] as usize;
+                // I is 16 bits wide, memory accesses through it are checked.
+                self.index_register = (self.index_register + self.registers[vx] as usize) & 0xFFFF;
             }
             GetKey(vx) => {
                 if !self.keypad.is_waiting() {
                     debug!("Waiting for key press to store in register {}", vx);
                     self.registers.pc -= 2;
                     self.keypad.set_wait(KeyWait::WaitingForPress(vx));
+                    self.events.push(Event::KeyWaitBegan { register: vx });
                 }
             }
             FontChar(vx) => {
                 debug!("Setting index to font character for register {}", vx);
-                // TODO: Implement FontChar logic here
+                // Only the low nibble picks the digit.
+                let digit = (self.registers[vx] & 0xF) as usize;
+                self.index_register = self.font_address as usize + digit * Font::GLYPH_HEIGHT;
             }
             BinDecConv(vx) => {
                 let val = self.registers[vx];
                 let (v1, v2, v3) = ((val / 100), (val / 10 % 10), (val % 10));
                 let idx = self.index_register;
-                self.memory.write(idx, v1);
-                self.memory.write(idx + 1, v2);
-                self.memory.write(idx + 2, v3);
+                self.write_memory(idx, v1)?;
+                self.write_memory(idx + 1, v2)?;
+                self.write_memory(idx + 2, v3)?;
                 debug!(
                     "Converting register {} to binary-coded decimal {} => ({}, {}, {})",
                     vx, val, v1, v2, v3
@@ -387,29 +891,92 @@ impl Chip8VM {
             }
             StoreMem(vx) => {
                 debug!("Storing registers 0 through {} into memory", vx);
-                let mut addr = self.index_register;
                 for vn in 0..=vx {
-                    self.memory.write(addr, self.registers[vn]);
-                    addr += 1;
+                    let addr = self.index_register + vn as usize;
+                    self.write_memory(addr, self.registers[vn])?;
                 }
+                self.advance_index_after_memory_op(vx);
             }
             LoadMem(vx) => {
                 debug!("Loading memory into registers 0 through {}", vx);
-                let mut addr = self.index_register;
                 for vn in 0..=vx {
-                    let val = self.memory.read(addr);
+                    let addr = self.index_register + vn as usize;
+                    let val = self.read_memory(addr)?;
                     self.registers[vn] = val;
-                    addr += 1;
                 }
+                self.advance_index_after_memory_op(vx);
             }
         }
         Ok(())
     }
+
+    /// Screen area touched by an 8 pixel wide sprite drawn at (x, y).
+    fn sprite_rect(&self, x: usize, y: usize, height: usize) -> Rect {
+        let span = |start: usize, len: usize, size: usize| {
+            if start + len <= size {
+                (start, len)
+            } else if self.quirks.wrap {
+                (0, size)
+            } else {
+                (start, size - start)
+            }
+        };
+        let (x, width) = span(x, 8, SCREEN_WIDTH);
+        let (y, height) = span(y, height, SCREEN_HEIGHT);
+        Rect {
+            x: x as u8,
+            y: y as u8,
+            width: width as u8,
+            height: height as u8,
+        }
+    }
+
+    fn advance_index_after_memory_op(&mut self, vx: RegNum) {
+        if self.quirks.memory_leave_i_unchanged {
+            return;
+        }
+        let step = if self.quirks.memory_increment_by_x {
+            vx as usize
+        } else {
+            vx as usize + 1
+        };
+        self.index_register = (self.index_register + step) & 0xFFFF;
+    }
+
+    /// Map `addr` into memory according to the fault policy. `None` means the
+    /// access should be ignored.
+    fn resolve_address(&self, addr: usize) -> Result<Option<usize>, VMError> {
+        if addr < RAM_SIZE {
+            return Ok(Some(addr));
+        }
+        match self.fault_policy {
+            FaultPolicy::Wrap => Ok(Some(addr & 0xFFF)),
+            FaultPolicy::Fault => Err(VMError::MemoryOutOfBounds {
+                addr,
+                pc: self.current_pc as u16,
+            }),
+            FaultPolicy::Ignore => Ok(None),
+        }
+    }
+
+    fn read_memory(&self, addr: usize) -> Result<u8, VMError> {
+        Ok(self
+            .resolve_address(addr)?
+            .map_or(0, |addr| self.memory.read(addr)))
+    }
+
+    fn write_memory(&mut self, addr: usize, val: u8) -> Result<(), VMError> {
+        if let Some(addr) = self.resolve_address(addr)? {
+            self.memory.write(addr, val);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::memory::VIP_STACK_ADDRESS;
 
     #[test]
     fn test_registers_8bits() {
@@ -458,4 +1025,468 @@ mod tests {
         assert!(vm.execute(Instruction::SubRight(0, 6)).is_ok());
         assert_eq!(vm.registers[0], 251);
     }
+
+    #[test]
+    fn test_load_rom_bytes() {
+        let mut vm = Chip8VM::new();
+        let info = vm.load_rom_bytes(&[0x12, 0x34]).unwrap();
+        assert_eq!(info.size, 2);
+        assert_eq!(vm.memory()[0x200..0x202], [0x12, 0x34]);
+        assert!(vm.load_rom_bytes(&[0; 3584]).is_ok());
+        assert!(matches!(
+            vm.load_rom_bytes(&[0; 3585]),
+            Err(VMError::RomTooLarge {
+                size: 3585,
+                max: 3584
+            })
+        ));
+        assert!(matches!(vm.load_rom_bytes(&[]), Err(VMError::RomEmpty())));
+    }
+
+    #[test]
+    fn test_load_rom_platform() {
+        let mut vm = Chip8VM::new();
+        vm.set_platform(Platform::Eti660).unwrap();
+        vm.load_rom_bytes(&[0x12, 0x34]).unwrap();
+        assert_eq!(vm.pc(), 0x600);
+        assert_eq!(vm.memory()[0x600..0x602], [0x12, 0x34]);
+        assert!(vm.load_rom_bytes(&[0; 2561]).is_err());
+    }
+
+    #[test]
+    fn test_load_rom_reader() {
+        let mut vm = Chip8VM::new();
+        let rom = [0x60, 0x05, 0x12, 0x00];
+        let info = vm.load_rom_reader(&rom[..]).unwrap();
+        assert_eq!(info, RomInfo::new(&rom));
+        assert_eq!(vm.memory()[0x200..0x204], rom);
+        assert!(vm.load_rom_reader(std::io::repeat(0)).is_err());
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        // V0 = random, V1 = random
+        let rom = [0xC0, 0xFF, 0xC1, 0xFF];
+        let run = |seed| {
+            let mut vm = Chip8VM::new();
+            vm.seed_rng(seed);
+            vm.load_rom_bytes(&rom).unwrap();
+            vm.cycle().unwrap();
+            vm.cycle().unwrap();
+            vm.registers()[..2].to_vec()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+    }
+
+    #[test]
+    fn test_quirks() {
+        let mut vm = Chip8VM::new();
+        vm.set_quirks(Platform::CosmacVip.quirks());
+        vm.load_rom_bytes(&[0x60, 0x00]).unwrap();
+        vm.registers[1] = 0b110;
+        assert!(vm.execute(Instruction::ShiftRight(0, 1)).is_ok());
+        assert_eq!(vm.registers[0], 0b11);
+        vm.registers[0xF] = 1;
+        assert!(vm.execute(Instruction::OR(0, 1)).is_ok());
+        assert_eq!(vm.registers[0xF], 0);
+        vm.index_register = 0x300;
+        assert!(vm.execute(Instruction::StoreMem(2)).is_ok());
+        assert_eq!(vm.index_register, 0x303);
+
+        // Sprites clip at the right edge and drawing waits for vblank.
+        vm.index_register = 0x400;
+        vm.memory.write(0x400, 0xFF);
+        vm.registers[0] = 60;
+        assert!(vm.execute(Instruction::Display(0, 2, 1)).is_ok());
+        assert!(!vm.display.get(0, 0));
+        assert!(vm.display.get(63, 0));
+        let pc = vm.pc();
+        assert!(vm.cycle().is_ok());
+        assert_eq!(vm.pc(), pc);
+        vm.tick_timers();
+        assert!(vm.cycle().is_ok());
+        assert_eq!(vm.pc(), pc + 2);
+
+        vm.set_quirks(Quirks::default());
+        vm.display.clear();
+        assert!(vm.execute(Instruction::Display(0, 2, 1)).is_ok());
+        assert!(vm.display.get(0, 0));
+
+        vm.set_quirks(Platform::SuperChip.quirks());
+        vm.registers[3] = 4;
+        assert!(vm.execute(Instruction::JumpOffset(0x310)).is_ok());
+        assert_eq!(vm.pc(), 0x314);
+    }
+
+    #[test]
+    fn test_font() {
+        let mut vm = Chip8VM::new();
+        let start = DEFAULT_FONT_ADDRESS as usize;
+        assert!(vm.memory()[start..start + Font::SIZE]
+            .iter()
+            .copied()
+            .eq(Font::VIP.bytes()));
+
+        // V0 = 0x1A, I = digit A, draw it at (0, 0)
+        vm.load_rom_bytes(&[0x60, 0x1A, 0xF0, 0x29, 0x61, 0x00, 0xD1, 0x15])
+            .unwrap();
+        for _ in 0..4 {
+            vm.cycle().unwrap();
+        }
+        assert_eq!(vm.index_register(), DEFAULT_FONT_ADDRESS + 50);
+        let top_row: Vec<bool> = (0..8).map(|x| vm.display.get(x, 0)).collect();
+        assert_eq!(
+            top_row,
+            [true, true, true, true, false, false, false, false]
+        );
+        assert!(!vm.display.get(1, 4));
+
+        let mut vm = Chip8VM::new();
+        vm.set_platform(Platform::Eti660).unwrap();
+        assert_eq!(vm.font(), Font::ETI660);
+        assert_eq!(vm.memory()[start + 5], 0x20);
+
+        vm.set_font_address(0).unwrap();
+        assert_eq!(vm.memory()[5], 0x20);
+        // The old copy is gone.
+        assert!(vm.memory()[start + Font::SIZE - 5..start + Font::SIZE]
+            .iter()
+            .all(|b| *b == 0));
+        vm.set_register(0, 0xA);
+        assert!(vm.execute(Instruction::FontChar(0)).is_ok());
+        assert_eq!(vm.index_register(), 50);
+        assert!(vm.set_font_address(0xFC0).is_err());
+        assert_eq!(vm.font_address(), 0);
+    }
+
+    #[test]
+    fn test_font_after_load() {
+        let mut vm = Chip8VM::new();
+        vm.set_font_address(0x200).unwrap();
+        vm.load_rom_bytes(&[0x12, 0x00]).unwrap();
+        assert_eq!(vm.set_font(Font::ETI660), Err(VMError::RomAlreadyLoaded()));
+        assert_eq!(vm.set_font_address(0x300), Err(VMError::RomAlreadyLoaded()));
+        assert_eq!(
+            vm.set_platform(Platform::SuperChip),
+            Err(VMError::RomAlreadyLoaded())
+        );
+        assert_eq!(vm.memory()[0x200..0x202], [0x12, 0x00]);
+        assert_eq!((vm.platform(), vm.font()), (Platform::CosmacVip, Font::VIP));
+    }
+
+    #[test]
+    fn test_setters() {
+        let mut vm = Chip8VM::new();
+        vm.set_register(0x13, 5);
+        assert_eq!(vm.registers()[3], 5);
+        vm.set_pc(0x1300);
+        assert_eq!(vm.pc(), 0x300);
+        vm.set_index_register(0x456);
+        assert_eq!(vm.index_register(), 0x456);
+        vm.poke(0x1300, 0xAB);
+        assert_eq!(vm.memory()[0x300], 0xAB);
+        vm.set_delay_timer(3);
+        vm.set_sound_timer(4);
+        assert_eq!((vm.delay_timer(), vm.sound_timer()), (3, 4));
+    }
+
+    #[test]
+    fn test_instruction_count() {
+        // draw, jump to self, then an invalid instruction
+        let mut vm = Chip8VM::new();
+        vm.set_quirks(Quirks {
+            vblank: true,
+            ..Quirks::default()
+        });
+        vm.load_rom_bytes(&[0xD0, 0x05, 0x12, 0x02, 0xFF, 0xFF])
+            .unwrap();
+        vm.cycle().unwrap();
+        // Waiting for the vblank isn't an instruction.
+        vm.cycle().unwrap();
+        assert_eq!(vm.instruction_count(), 1);
+        vm.tick_timers();
+        vm.cycle().unwrap();
+        vm.cycle().unwrap();
+        assert_eq!(vm.instruction_count(), 3);
+
+        vm.set_pc(0x204);
+        assert!(vm.cycle().is_err());
+        assert_eq!(vm.instruction_count(), 3);
+    }
+
+    #[test]
+    fn test_rom_load_failure() {
+        let path = String::from("no/such/rom.ch8");
+        let Err(VMError::RomLoadFailure(err)) = Chip8VM::new().load_rom(&path) else {
+            panic!("expected a load failure");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with("no/such/rom.ch8: "));
+        assert!(err.to_string().len() > path.len() + 2);
+    }
+
+    #[test]
+    fn test_reset() {
+        // 0x200: V0 = 0x12, I = font, delay = V0, call 0x208, 0x208: draw, wait for a key
+        let rom = [
+            0x60, 0x12, 0xA0, 0x50, 0xF0, 0x15, 0x22, 0x08, 0xD0, 0x05, 0xF1, 0x0A,
+        ];
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&rom).unwrap();
+        for _ in 0..6 {
+            vm.cycle().unwrap();
+        }
+        vm.handle_key(0x5, true);
+        assert!(vm.is_waiting_for_key());
+        while vm.poll_event().is_some() {}
+
+        vm.reset();
+        assert_eq!(vm.pc(), 0x200);
+        assert_eq!(vm.registers(), &[0; NUM_REGISTERS]);
+        assert_eq!(vm.index_register(), 0);
+        assert_eq!(vm.delay_timer(), 0);
+        assert_eq!(vm.call_stack().len(), 0);
+        assert!(!vm.is_waiting_for_key());
+        assert!(vm.display().rows().iter().all(|row| *row == 0));
+        assert_eq!(vm.poll_event(), Some(Event::DisplayCleared));
+        // The ROM is still there.
+        assert_eq!(&vm.memory()[0x200..0x200 + rom.len()], &rom);
+        vm.cycle().unwrap();
+        assert_eq!(vm.registers()[0], 0x12);
+    }
+
+    #[test]
+    fn test_call_stack() {
+        // 0x200: call 0x200 forever
+        let recurse = [0x22, 0x00];
+        for (platform, depth) in [(Platform::CosmacVip, 12), (Platform::SuperChip, 16)] {
+            let mut vm = Chip8VM::new();
+            vm.set_platform(platform).unwrap();
+            vm.load_rom_bytes(&recurse).unwrap();
+            for _ in 0..depth {
+                vm.cycle().unwrap();
+            }
+            assert_eq!(vm.call_stack().len(), depth);
+            assert!(matches!(vm.cycle(), Err(VMError::StackOverflow())));
+        }
+
+        // Returning with nothing on the stack is an error, not a no-op.
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&[0x00, 0xEE]).unwrap();
+        assert!(matches!(vm.cycle(), Err(VMError::StackUnderflow())));
+
+        // call 0x206, then the subroutine overwrites its return address and returns
+        let rom = [
+            0x22, 0x06, 0x00, 0x00, 0x00, 0x00, 0xAE, 0xA0, 0x60, 0x03, 0xF0, 0x55, 0x00, 0xEE,
+        ];
+        let mut vm = Chip8VM::new();
+        vm.set_stack_mode(StackMode::Memory {
+            address: VIP_STACK_ADDRESS,
+        });
+        vm.load_rom_bytes(&rom).unwrap();
+        vm.cycle().unwrap();
+        assert_eq!(*vm.call_stack(), [0x202]);
+        assert_eq!(vm.memory()[0xEA0..0xEA2], [0x02, 0x02]);
+
+        let mut restored = Chip8VM::new();
+        restored.set_stack_mode(vm.stack_mode());
+        restored.load_state(&vm.save_state()).unwrap();
+        assert_eq!(*restored.call_stack(), [0x202]);
+
+        for _ in 0..4 {
+            vm.cycle().unwrap();
+        }
+        assert_eq!(vm.pc(), 0x302);
+        assert!(vm.call_stack().is_empty());
+    }
+
+    #[test]
+    fn test_halted_twice() {
+        // 200: spin, 202: V0 = 1, 204: spin, 206: jump back to 200.
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&[0x12, 0x00, 0x60, 0x01, 0x12, 0x04, 0x12, 0x00])
+            .unwrap();
+        let halts = |vm: &mut Chip8VM| {
+            for _ in 0..4 {
+                vm.cycle().unwrap();
+            }
+            let mut halts = Vec::new();
+            while let Some(event) = vm.poll_event() {
+                if let Event::Halted { pc } = event {
+                    halts.push(pc);
+                }
+            }
+            halts
+        };
+        assert_eq!(halts(&mut vm), [0x200]);
+        assert!(halts(&mut vm).is_empty());
+
+        // Something else runs, then the same spin loop again.
+        vm.set_pc(0x206);
+        assert_eq!(halts(&mut vm), [0x200]);
+
+        // Straight from one spin loop to another.
+        vm.set_pc(0x204);
+        assert_eq!(halts(&mut vm), [0x204]);
+
+        // V0 = 1 runs in between, so the same loop is reported again.
+        vm.set_pc(0x202);
+        assert_eq!(halts(&mut vm), [0x204]);
+    }
+
+    #[test]
+    fn test_events() {
+        let rom = [
+            0x00, 0xE0, // clear
+            0x60, 0x3C, // V0 = 60
+            0xD0, 0x15, // draw 5 rows at (60, 0)
+            0x22, 0x0E, // call 0x20E
+            0xF1, 0x18, // sound timer = V1 (2)
+            0xF2, 0x0A, // wait for key into V2
+            0x12, 0x0C, // halt
+            0x61, 0x02, // 0x20E: V1 = 2
+            0x00, 0xEE, // return
+        ];
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&rom).unwrap();
+        for _ in 0..9 {
+            vm.cycle().unwrap();
+        }
+        vm.tick_timers();
+        vm.handle_key(7, true);
+        vm.handle_key(7, false);
+        vm.tick_timers();
+        for _ in 0..3 {
+            vm.cycle().unwrap();
+        }
+
+        let events: Vec<Event> = core::iter::from_fn(|| vm.poll_event()).collect();
+        assert_eq!(
+            events,
+            [
+                Event::DisplayCleared,
+                Event::DisplayDrawn(Rect {
+                    x: 0,
+                    y: 0,
+                    width: 64,
+                    height: 5
+                }),
+                Event::SubroutineCalled {
+                    addr: 0x20E,
+                    return_addr: 0x208
+                },
+                Event::SubroutineReturned { addr: 0x208 },
+                Event::SoundStarted,
+                Event::KeyWaitBegan { register: 2 },
+                Event::KeyWaitEnded { key: 7 },
+                Event::SoundStopped,
+                Event::Halted { pc: 0x20C },
+            ]
+        );
+
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&[0xFF, 0xFF]).unwrap();
+        assert!(vm.cycle().is_err());
+        assert_eq!(
+            vm.poll_event(),
+            Some(Event::Fault(VMError::UnknownInstruction(0xFFFF)))
+        );
+        assert_eq!(vm.dropped_events(), 0);
+    }
+
+    #[test]
+    fn test_fault_policy() {
+        // I = 0xFFE, store V0..V2, then BCD of V0
+        let rom = [0xAF, 0xFE, 0xF2, 0x55, 0xF0, 0x33];
+        let run = |policy| {
+            let mut vm = Chip8VM::new();
+            vm.set_fault_policy(policy);
+            vm.load_rom_bytes(&rom).unwrap();
+            vm.registers.data[..3].copy_from_slice(&[201, 2, 3]);
+            vm.cycle().unwrap();
+            let result = vm.cycle();
+            (vm, result)
+        };
+
+        let (vm, result) = run(FaultPolicy::Wrap);
+        assert!(result.is_ok());
+        assert_eq!(vm.memory()[0xFFE..], [201, 2]);
+        assert_eq!(vm.memory()[0], 3);
+
+        let (vm, result) = run(FaultPolicy::Fault);
+        assert!(matches!(
+            result,
+            Err(VMError::MemoryOutOfBounds {
+                addr: 0x1000,
+                pc: 0x202
+            })
+        ));
+        assert_eq!(vm.memory()[0xFFE..], [201, 2]);
+
+        let (mut vm, result) = run(FaultPolicy::Ignore);
+        assert!(result.is_ok());
+        assert_eq!(vm.memory()[0], 0);
+        assert!(vm.cycle().is_ok());
+        assert_eq!(vm.memory()[0xFFE..], [2, 0]);
+
+        // I stays 16 bits wide however much is added to it.
+        let mut vm = Chip8VM::new();
+        vm.index_register = 0xFFFF;
+        vm.registers[0] = 0xFF;
+        assert!(vm.execute(Instruction::AddToIndex(0)).is_ok());
+        assert_eq!(vm.index_register(), 0xFE);
+
+        // PC running off the end of memory.
+        for policy in [FaultPolicy::Wrap, FaultPolicy::Fault] {
+            let mut vm = Chip8VM::new();
+            vm.set_fault_policy(policy);
+            // 0x6000 split across the end and start of memory.
+            vm.memory.write(0xFFF, 0x60);
+            vm.memory.write(0, 0x00);
+            vm.registers.pc = 0xFFF;
+            assert_eq!(vm.cycle().is_err(), policy == FaultPolicy::Fault);
+        }
+
+        // A sprite at I = 0xFFE drawn on the last two rows: its last two
+        // rows would be read past memory but are clipped.
+        for wrap in [false, true] {
+            let mut vm = Chip8VM::new();
+            vm.set_fault_policy(FaultPolicy::Fault);
+            vm.quirks.wrap = wrap;
+            vm.index_register = 0xFFE;
+            vm.registers[0] = 30;
+            let result = vm.execute(Instruction::Display(0, 0, 4));
+            assert_eq!(result.is_err(), wrap);
+        }
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        let mut vm = Chip8VM::new();
+        // V0 = 5, I = 0x50, call 0x208, draw 5 rows at (V0, V0)
+        let rom = [0x60, 0x05, 0xA0, 0x50, 0x22, 0x08, 0x00, 0x00, 0xD0, 0x05];
+        vm.load_rom_bytes(&rom).unwrap();
+        for _ in 0..4 {
+            vm.cycle().unwrap();
+        }
+        vm.handle_key(0xA, true);
+        let state = vm.save_state();
+
+        let mut restored = Chip8VM::new();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.pc(), vm.pc());
+        assert_eq!(*restored.call_stack(), [0x206]);
+        assert_eq!(restored.get_frame_buffer(), vm.get_frame_buffer());
+
+        assert!(restored.load_state(&state[..state.len() - 1]).is_err());
+        assert!(restored.load_state(b"garbage").is_err());
+        assert_eq!(restored.save_state(), state);
+
+        // A skip on the last instruction leaves PC past the end of memory.
+        vm.registers.pc = 0x1001;
+        restored.load_state(&vm.save_state()).unwrap();
+        assert_eq!(restored.pc(), 0x1001);
+    }
 }
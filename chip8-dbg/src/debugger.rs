@@ -0,0 +1,547 @@
+use chip8_core::display::Display;
+use chip8_core::instructions::Instruction;
+use chip8_core::vm::{Chip8VM, VMError, DEFAULT_CYCLES_PER_FRAME};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const DISAS_LINES: u64 = 10;
+
+pub const HELP: &str = "\
+Addresses are hex (200, 0x200), counts and values decimal unless 0x-prefixed.
+  break [addr]          set a breakpoint, or list them
+  watch [addr]          stop when the byte at addr changes, or list watches
+  delete [addr]         remove a breakpoint/watch at addr, or all of them
+  step [n], s           execute n instructions (default 1)
+  next, n               step over CALL
+  finish                run until the current subroutine returns
+  continue, c           run until a breakpoint, watch, fault, halt or key wait;
+                        Ctrl-C stops any run
+  regs                  show registers, timers and the call stack
+  x/<n>b <addr>         dump n bytes of memory
+  disas [addr] [n]      disassemble n instructions (default at PC)
+  set <target> = <val>  target is v0-vf, i, pc, dt, st or [addr]
+  key <k> down|up       press or release key 0-F
+  frame                 print the screen
+  save [file]           snapshot the VM to a file, or in memory
+  load [file]           restore a snapshot
+  source <file>         run commands from a file
+  quit, q               exit";
+
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+enum Stop {
+    Finished,
+    Breakpoint,
+    Watch { addr: u16, old: u8, new: u8 },
+    Fault(VMError),
+    Halted,
+    WaitingForKey,
+    Interrupted,
+}
+
+pub struct Debugger {
+    vm: Chip8VM,
+    breakpoints: BTreeSet<u16>,
+    // watched address and the value it had when last checked.
+    watches: BTreeMap<u16, u8>,
+    snapshot: Option<Vec<u8>>,
+    cycles: u64,
+    // Set from the Ctrl-C handler to stop a run.
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Debugger {
+    pub fn new(vm: Chip8VM) -> Debugger {
+        Debugger {
+            vm,
+            breakpoints: BTreeSet::new(),
+            watches: BTreeMap::new(),
+            snapshot: None,
+            cycles: 0,
+            interrupt: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Flag that stops the current run when set, e.g. from a Ctrl-C handler.
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.interrupt)
+    }
+
+    /// Run a single command line.
+    pub fn execute(&mut self, line: &str) -> Result<Flow, String> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match cmd {
+            "" => {}
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Ok(Flow::Quit),
+            "break" | "b" => match args.first() {
+                Some(addr) => {
+                    let addr = parse_addr(addr)?;
+                    self.breakpoints.insert(addr);
+                    println!("Breakpoint set at {:#05X}", addr);
+                }
+                None => {
+                    for addr in &self.breakpoints {
+                        println!("  {:#05X}", addr);
+                    }
+                }
+            },
+            "watch" => match args.first() {
+                Some(addr) => {
+                    let addr = parse_addr(addr)?;
+                    let val = self.vm.memory()[addr as usize];
+                    self.watches.insert(addr, val);
+                    println!("Watching {:#05X} = {:#04X}", addr, val);
+                }
+                None => {
+                    for (addr, val) in &self.watches {
+                        println!("  {:#05X} = {:#04X}", addr, val);
+                    }
+                }
+            },
+            "delete" | "d" => match args.first() {
+                Some(addr) => {
+                    let addr = parse_addr(addr)?;
+                    let removed =
+                        self.breakpoints.remove(&addr) | self.watches.remove(&addr).is_some();
+                    if !removed {
+                        return Err(format!("Nothing set at {:#05X}", addr));
+                    }
+                }
+                None => {
+                    self.breakpoints.clear();
+                    self.watches.clear();
+                }
+            },
+            "step" | "s" => {
+                let count = match args.first() {
+                    Some(n) => parse_count(n)?,
+                    None => 1,
+                };
+                let stop = self.run(Some(count), |_| false);
+                self.report(stop);
+            }
+            "next" | "n" => {
+                let stop =
+                    if let Instruction::CallSubroutine(_) = self.vm.instruction_at(self.vm.pc()) {
+                        let depth = self.vm.call_stack().len();
+                        let return_addr = self.vm.pc() + 2;
+                        self.run(None, |vm| {
+                            vm.pc() == return_addr && vm.call_stack().len() == depth
+                        })
+                    } else {
+                        self.run(Some(1), |_| false)
+                    };
+                self.report(stop);
+            }
+            "finish" => {
+                let depth = self.vm.call_stack().len();
+                if depth == 0 {
+                    return Err("Not in a subroutine".to_string());
+                }
+                let stop = self.run(None, |vm| vm.call_stack().len() < depth);
+                self.report(stop);
+            }
+            "continue" | "c" => {
+                let stop = self.run(None, |_| false);
+                self.report(stop);
+            }
+            "regs" | "r" => self.print_registers(),
+            _ if cmd == "x" || cmd.starts_with("x/") => {
+                let count = match cmd.strip_prefix("x/") {
+                    Some(fmt) => parse_count(fmt.strip_suffix('b').unwrap_or(fmt))?,
+                    None => 16,
+                };
+                let addr = parse_addr(args.first().ok_or("Usage: x/<n>b <addr>")?)?;
+                self.dump(addr, count);
+            }
+            "disas" => {
+                let addr = match args.first() {
+                    Some(addr) => parse_addr(addr)?,
+                    None => self.vm.pc(),
+                };
+                let count = match args.get(1) {
+                    Some(n) => parse_count(n)?,
+                    None => DISAS_LINES,
+                };
+                self.disassemble(addr, count);
+            }
+            "set" => self.set(rest)?,
+            "key" => {
+                let (Some(key), Some(state)) = (args.first(), args.get(1)) else {
+                    return Err("Usage: key <0-F> down|up".to_string());
+                };
+                let key = u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|k| *k <= 0xF)
+                    .ok_or_else(|| format!("Unknown key: {}", key))?;
+                let pressed = match *state {
+                    "down" => true,
+                    "up" => false,
+                    _ => return Err("Usage: key <0-F> down|up".to_string()),
+                };
+                self.vm.handle_key(key, pressed);
+            }
+            "frame" => self.print_frame(),
+            "save" => {
+                let state = self.vm.save_state();
+                match args.first() {
+                    Some(path) => fs::write(path, state).map_err(|e| format!("{}: {}", path, e))?,
+                    None => self.snapshot = Some(state),
+                }
+                println!("Saved");
+            }
+            "load" => {
+                let state = match args.first() {
+                    Some(path) => fs::read(path).map_err(|e| format!("{}: {}", path, e))?,
+                    None => self.snapshot.clone().ok_or("No snapshot saved")?,
+                };
+                self.vm.load_state(&state).map_err(|e| e.to_string())?;
+                self.refresh_watches();
+                self.print_location();
+            }
+            "source" => {
+                let path = args.first().ok_or("Usage: source <file>")?;
+                return self.source(path);
+            }
+            _ => return Err(format!("Unknown command: {} (try help)", cmd)),
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Run each line of a command file, stopping at the first error. Lines
+    /// starting with # are comments.
+    pub fn source(&mut self, path: &str) -> Result<Flow, String> {
+        let script = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for line in script.lines().filter(|l| !l.trim_start().starts_with('#')) {
+            if let Flow::Quit = self.execute(line)? {
+                return Ok(Flow::Quit);
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    // Execute until `max_steps` instructions ran or `done` holds, stopping
+    // early at breakpoints, watches, faults and interrupts. Unbounded runs
+    // also stop when the ROM halts or waits for a key, since nothing would
+    // change.
+    fn run(&mut self, max_steps: Option<u64>, done: impl Fn(&Chip8VM) -> bool) -> Stop {
+        // Forget a Ctrl-C pressed while no run was going.
+        self.interrupt.store(false, Ordering::Relaxed);
+        let mut steps = 0;
+        loop {
+            if max_steps.is_some_and(|max| steps >= max) {
+                return Stop::Finished;
+            }
+            if self.interrupt.swap(false, Ordering::Relaxed) {
+                return Stop::Interrupted;
+            }
+            if let Err(e) = self.vm.cycle() {
+                return Stop::Fault(e);
+            }
+            steps += 1;
+            self.cycles += 1;
+            if self.cycles.is_multiple_of(DEFAULT_CYCLES_PER_FRAME as u64) {
+                self.vm.tick_timers();
+            }
+            // Events aren't used here, drain them so they don't pile up.
+            while self.vm.poll_event().is_some() {}
+
+            for (&addr, old) in self.watches.iter_mut() {
+                let new = self.vm.memory()[addr as usize];
+                if new != *old {
+                    let stop = Stop::Watch {
+                        addr,
+                        old: *old,
+                        new,
+                    };
+                    *old = new;
+                    return stop;
+                }
+            }
+            if done(&self.vm) {
+                return Stop::Finished;
+            }
+            let pc = self.vm.pc();
+            if self.breakpoints.contains(&pc) {
+                return Stop::Breakpoint;
+            }
+            if max_steps.is_none() {
+                if matches!(self.vm.instruction_at(pc), Instruction::Jump(addr) if addr == pc) {
+                    return Stop::Halted;
+                }
+                if self.vm.is_waiting_for_key() {
+                    return Stop::WaitingForKey;
+                }
+            }
+        }
+    }
+
+    fn report(&self, stop: Stop) {
+        match stop {
+            Stop::Finished => {}
+            Stop::Breakpoint => println!("Breakpoint at {:#05X}", self.vm.pc()),
+            Stop::Watch { addr, old, new } => {
+                println!("Watch {:#05X}: {:#04X} -> {:#04X}", addr, old, new)
+            }
+            Stop::Fault(e) => println!("Fault: {}", e),
+            Stop::Halted => println!("Halted, {:#05X} jumps to itself", self.vm.pc()),
+            Stop::WaitingForKey => println!("Waiting for a key, press one with `key <k> down`"),
+            Stop::Interrupted => println!("Interrupted"),
+        }
+        self.print_location();
+    }
+
+    fn print_location(&self) {
+        let pc = self.vm.pc();
+        println!("=> {:03X}  {}", pc, self.vm.instruction_at(pc));
+    }
+
+    // Watched values may have changed behind our back, e.g. after `load`.
+    fn refresh_watches(&mut self) {
+        for (&addr, val) in self.watches.iter_mut() {
+            *val = self.vm.memory()[addr as usize];
+        }
+    }
+
+    fn print_registers(&self) {
+        let regs = self.vm.registers();
+        for row in regs.chunks(4).enumerate() {
+            let (row, values) = row;
+            let cols: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(col, v)| format!("V{:X}={:02X}", row * 4 + col, v))
+                .collect();
+            println!("{}", cols.join(" "));
+        }
+        println!(
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+            self.vm.pc(),
+            self.vm.index_register(),
+            self.vm.delay_timer(),
+            self.vm.sound_timer()
+        );
+        let stack: Vec<String> = self
+            .vm
+            .call_stack()
+            .iter()
+            .map(|addr| format!("{:03X}", addr))
+            .collect();
+        println!("Stack: [{}]", stack.join(" "));
+        println!("Cycles: {}", self.cycles);
+    }
+
+    // Counts past the end wrap around, so at most all of memory is shown.
+    fn dump(&self, addr: u16, count: u64) {
+        let memory = self.vm.memory();
+        let count = count.min(memory.len() as u64) as usize;
+        for start in (0..count).step_by(8) {
+            let bytes: Vec<String> = (start..count.min(start + 8))
+                .map(|i| format!("{:02X}", memory[(addr as usize + i) % memory.len()]))
+                .collect();
+            println!(
+                "{:03X}: {}",
+                (addr as usize + start) % memory.len(),
+                bytes.join(" ")
+            );
+        }
+    }
+
+    // Like `dump`, at most one pass over memory.
+    fn disassemble(&self, addr: u16, count: u64) {
+        for i in 0..count.min(0x800) as u16 {
+            let addr = addr.wrapping_add(i * 2) & 0xFFF;
+            let marker = if addr == self.vm.pc() { "=>" } else { "  " };
+            let bp = if self.breakpoints.contains(&addr) {
+                "*"
+            } else {
+                " "
+            };
+            println!(
+                "{}{} {:03X}  {}",
+                marker,
+                bp,
+                addr,
+                self.vm.instruction_at(addr)
+            );
+        }
+    }
+
+    fn set(&mut self, assignment: &str) -> Result<(), String> {
+        let usage = "Usage: set <v0-vf|i|pc|dt|st|[addr]> = <value>";
+        let (target, value) = assignment.split_once('=').ok_or(usage)?;
+        let target = target.trim().to_ascii_lowercase();
+        let value = parse_value(value.trim())?;
+        let byte = || u8::try_from(value).map_err(|_| format!("{} doesn't fit in a byte", value));
+        match target.as_str() {
+            "i" => self.vm.set_index_register(value),
+            "pc" => self.vm.set_pc(value),
+            "dt" => self.vm.set_delay_timer(byte()?),
+            "st" => self.vm.set_sound_timer(byte()?),
+            _ => {
+                if let Some(reg) = target.strip_prefix('v') {
+                    let reg = u8::from_str_radix(reg, 16)
+                        .ok()
+                        .filter(|r| *r <= 0xF)
+                        .ok_or_else(|| format!("Unknown register: {}", target))?;
+                    self.vm.set_register(reg, byte()?);
+                } else if let Some(addr) =
+                    target.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+                {
+                    self.vm.poke(parse_addr(addr)?, byte()?);
+                    self.refresh_watches();
+                } else {
+                    return Err(usage.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_frame(&self) {
+        let display = self.vm.display();
+        for y in 0..Display::HEIGHT {
+            let row: String = (0..Display::WIDTH)
+                .map(|x| if display.get(x, y) { '#' } else { '.' })
+                .collect();
+            println!("{}", row);
+        }
+    }
+}
+
+fn parse_addr(s: &str) -> Result<u16, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(digits, 16)
+        .ok()
+        .filter(|addr| *addr <= 0xFFF)
+        .ok_or_else(|| format!("Invalid address: {}", s))
+}
+
+fn parse_value(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("Invalid value: {}", s))
+}
+
+fn parse_count(s: &str) -> Result<u64, String> {
+    s.parse().map_err(|_| format!("Invalid count: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn debugger(rom: &[u8]) -> Debugger {
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(rom).unwrap();
+        Debugger::new(vm)
+    }
+
+    // 200: CALL 208, 202: HALT, 204-207: unused,
+    // 208: V0 = 5, 20A: RET
+    const CALL_ROM: [u8; 12] = [
+        0x22, 0x08, 0x12, 0x02, 0x00, 0x00, 0x00, 0x00, 0x60, 0x05, 0x00, 0xEE,
+    ];
+
+    #[test]
+    fn test_break() {
+        let mut dbg = debugger(&CALL_ROM);
+        dbg.execute("break 20A").unwrap();
+        dbg.execute("continue").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x20A);
+        assert_eq!(dbg.vm.registers()[0], 5);
+
+        dbg.execute("delete 20A").unwrap();
+        assert!(dbg.execute("delete 20A").is_err());
+        dbg.execute("continue").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x202);
+    }
+
+    #[test]
+    fn test_next() {
+        let mut dbg = debugger(&CALL_ROM);
+        dbg.execute("next").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x202);
+        assert_eq!(dbg.vm.registers()[0], 5);
+        assert!(dbg.vm.call_stack().is_empty());
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut dbg = debugger(&CALL_ROM);
+        assert!(dbg.execute("finish").is_err());
+        dbg.execute("step").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x208);
+        dbg.execute("finish").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x202);
+        assert_eq!(dbg.vm.registers()[0], 5);
+    }
+
+    #[test]
+    fn test_watch() {
+        // I = 300, V0 = 7, store V0 at I, then halt.
+        let mut dbg = debugger(&[0xA3, 0x00, 0x60, 0x07, 0xF0, 0x55, 0x12, 0x06]);
+        dbg.execute("watch 300").unwrap();
+        dbg.execute("continue").unwrap();
+        assert_eq!(dbg.vm.pc(), 0x206);
+        assert_eq!(dbg.watches[&0x300], 7);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut dbg = debugger(&CALL_ROM);
+        dbg.execute("set v3 = 0x12").unwrap();
+        dbg.execute("set i = 300").unwrap();
+        dbg.execute("set pc = 0x208").unwrap();
+        dbg.execute("set dt = 60").unwrap();
+        dbg.execute("set [300] = 255").unwrap();
+        assert_eq!(dbg.vm.registers()[3], 0x12);
+        assert_eq!(dbg.vm.index_register(), 300);
+        assert_eq!(dbg.vm.pc(), 0x208);
+        assert_eq!(dbg.vm.delay_timer(), 60);
+        assert_eq!(dbg.vm.memory()[0x300], 255);
+
+        assert!(dbg.execute("set vg = 1").is_err());
+        assert!(dbg.execute("set st = 256").is_err());
+        assert!(dbg.execute("set [1000] = 1").is_err());
+        assert!(dbg.execute("set v0 1").is_err());
+    }
+
+    #[test]
+    fn test_examine() {
+        let mut dbg = debugger(&CALL_ROM);
+        dbg.execute("x/16b 200").unwrap();
+        dbg.execute("x FFC").unwrap();
+        assert!(dbg.execute("x/16b").is_err());
+        assert!(dbg.execute("x/zb 200").is_err());
+        // Huge counts stop after one pass over memory.
+        dbg.execute("x/18446744073709551615b 200").unwrap();
+        dbg.execute("disas 200 40000").unwrap();
+    }
+
+    #[test]
+    fn test_interrupt() {
+        // 200: V0 += 1, 202: jump to 200, forever.
+        let mut dbg = debugger(&[0x70, 0x01, 0x12, 0x00]);
+        let interrupt = dbg.interrupt_flag();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        dbg.execute("continue").unwrap();
+        handle.join().unwrap();
+        assert!(dbg.cycles > 0);
+    }
+}
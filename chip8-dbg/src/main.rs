@@ -0,0 +1,118 @@
+mod debugger;
+
+use chip8_core::platform::Platform;
+use chip8_core::vm::Chip8VM;
+use debugger::{Debugger, Flow};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+const HISTORY_FILE: &str = ".chip8_dbg_history";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (rom_path, platform, scripts) = match parse_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(msg) => {
+            println!("{}", msg);
+            println!(
+                "Usage: chip8-dbg [--platform <name>] [-x <commands file>]... <path/to/rom.ch8>"
+            );
+            return;
+        }
+    };
+
+    let mut vm = Chip8VM::new();
+    vm.set_platform(platform)
+        .expect("platform is set before the ROM is loaded");
+    if let Err(e) = vm.load_rom(&rom_path) {
+        println!("Failed to load ROM: {}", e);
+        return;
+    }
+
+    let mut debugger = Debugger::new(vm);
+    // Ctrl-C stops a run and returns to the prompt. rustyline reads it as a
+    // key while editing, so this only fires mid-run.
+    let interrupt = debugger.interrupt_flag();
+    if let Err(e) = ctrlc::set_handler(move || interrupt.store(true, Ordering::Relaxed)) {
+        println!("Couldn't install the Ctrl-C handler: {}", e);
+    }
+    for script in &scripts {
+        match debugger.source(script) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Quit) => return,
+            Err(msg) => {
+                println!("{}", msg);
+                return;
+            }
+        }
+    }
+
+    if let Err(e) = repl(&mut debugger) {
+        println!("Terminal error: {}", e);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(String, Platform, Vec<String>), String> {
+    let mut rom_path = None;
+    let mut platform = Platform::default();
+    let mut scripts = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--platform" => {
+                let name = iter.next().ok_or("Missing value for --platform")?;
+                platform =
+                    Platform::parse(name).ok_or_else(|| format!("Unknown platform: {}", name))?;
+            }
+            "-x" => scripts.push(iter.next().ok_or("Missing value for -x")?.clone()),
+            _ if rom_path.is_none() && !arg.starts_with('-') => rom_path = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let rom_path = rom_path.ok_or("Missing ROM path")?;
+    Ok((rom_path, platform, scripts))
+}
+
+fn repl(debugger: &mut Debugger) -> rustyline::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history {
+        // Missing on first run.
+        let _ = editor.load_history(path);
+    }
+
+    println!("Type help for a list of commands.");
+    let mut last = String::new();
+    loop {
+        let line = match editor.readline("(chip8) ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e),
+        };
+        // An empty line repeats the last command, like gdb.
+        if line.trim().is_empty() {
+            if last.is_empty() {
+                continue;
+            }
+        } else {
+            editor.add_history_entry(line.as_str())?;
+            last = line;
+        }
+        match debugger.execute(&last) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Quit) => break,
+            Err(msg) => println!("{}", msg),
+        }
+    }
+
+    if let Some(path) = &history {
+        editor.save_history(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+/*
+ * Minimal libretro frontend used to smoke test the core without RetroArch.
+ *
+ *   cargo build --release
+ *   cc -o harness harness/harness.c -ldl
+ *   ./harness target/release/libchip8_libretro.so ../bin/test-roms/ibm_logo.ch8
+ *
+ * Loads the core with dlopen, runs a few seconds of frames, checks the video
+ * and audio callbacks fire with the expected sizes, and round-trips a save
+ * state. Exits non-zero on any failure.
+ */
+#include <dlfcn.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+struct retro_system_info {
+    const char *library_name;
+    const char *library_version;
+    const char *valid_extensions;
+    bool need_fullpath;
+    bool block_extract;
+};
+
+struct retro_game_geometry {
+    unsigned base_width, base_height, max_width, max_height;
+    float aspect_ratio;
+};
+
+struct retro_system_timing {
+    double fps, sample_rate;
+};
+
+struct retro_system_av_info {
+    struct retro_game_geometry geometry;
+    struct retro_system_timing timing;
+};
+
+struct retro_game_info {
+    const char *path;
+    const void *data;
+    size_t size;
+    const char *meta;
+};
+
+static unsigned video_frames, video_width, video_height;
+static uint32_t video_hash;
+static size_t audio_frames;
+
+static bool environment(unsigned cmd, void *data) {
+    (void)data;
+    return cmd == 10; /* RETRO_ENVIRONMENT_SET_PIXEL_FORMAT */
+}
+
+static void video_refresh(const void *data, unsigned width, unsigned height, size_t pitch) {
+    const uint8_t *bytes = data;
+    video_frames++;
+    video_width = width;
+    video_height = height;
+    /* FNV-1a over the frame so save state restores can be compared. */
+    video_hash = 2166136261u;
+    for (size_t i = 0; i < pitch * height; i++) {
+        video_hash = (video_hash ^ bytes[i]) * 16777619u;
+    }
+}
+
+static void audio_sample(int16_t left, int16_t right) {
+    (void)left;
+    (void)right;
+}
+
+static size_t audio_sample_batch(const int16_t *data, size_t frames) {
+    (void)data;
+    audio_frames += frames;
+    return frames;
+}
+
+static void input_poll(void) {}
+
+static int16_t input_state(unsigned port, unsigned device, unsigned index, unsigned id) {
+    (void)port;
+    (void)device;
+    (void)index;
+    (void)id;
+    return 0;
+}
+
+#define LOAD(name)                                                   \
+    name##_t name = (name##_t)dlsym(core, #name);                    \
+    if (!name) {                                                     \
+        fprintf(stderr, "missing symbol %s\n", #name);               \
+        return 1;                                                    \
+    }
+
+typedef unsigned (*retro_api_version_t)(void);
+typedef void (*retro_init_t)(void);
+typedef void (*retro_deinit_t)(void);
+typedef void (*retro_get_system_info_t)(struct retro_system_info *);
+typedef void (*retro_get_system_av_info_t)(struct retro_system_av_info *);
+typedef void (*retro_set_environment_t)(bool (*)(unsigned, void *));
+typedef void (*retro_set_video_refresh_t)(void (*)(const void *, unsigned, unsigned, size_t));
+typedef void (*retro_set_audio_sample_t)(void (*)(int16_t, int16_t));
+typedef void (*retro_set_audio_sample_batch_t)(size_t (*)(const int16_t *, size_t));
+typedef void (*retro_set_input_poll_t)(void (*)(void));
+typedef void (*retro_set_input_state_t)(int16_t (*)(unsigned, unsigned, unsigned, unsigned));
+typedef bool (*retro_load_game_t)(const struct retro_game_info *);
+typedef void (*retro_unload_game_t)(void);
+typedef void (*retro_run_t)(void);
+typedef size_t (*retro_serialize_size_t)(void);
+typedef bool (*retro_serialize_t)(void *, size_t);
+typedef bool (*retro_unserialize_t)(const void *, size_t);
+
+#define CHECK(cond)                                                  \
+    if (!(cond)) {                                                   \
+        fprintf(stderr, "check failed: %s\n", #cond);                \
+        return 1;                                                    \
+    }
+
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        fprintf(stderr, "usage: %s <core.so> <rom.ch8>\n", argv[0]);
+        return 2;
+    }
+
+    void *core = dlopen(argv[1], RTLD_NOW);
+    if (!core) {
+        fprintf(stderr, "dlopen: %s\n", dlerror());
+        return 1;
+    }
+
+    LOAD(retro_api_version);
+    LOAD(retro_init);
+    LOAD(retro_deinit);
+    LOAD(retro_get_system_info);
+    LOAD(retro_get_system_av_info);
+    LOAD(retro_set_environment);
+    LOAD(retro_set_video_refresh);
+    LOAD(retro_set_audio_sample);
+    LOAD(retro_set_audio_sample_batch);
+    LOAD(retro_set_input_poll);
+    LOAD(retro_set_input_state);
+    LOAD(retro_load_game);
+    LOAD(retro_unload_game);
+    LOAD(retro_run);
+    LOAD(retro_serialize_size);
+    LOAD(retro_serialize);
+    LOAD(retro_unserialize);
+
+    CHECK(retro_api_version() == 1);
+
+    struct retro_system_info info;
+    retro_get_system_info(&info);
+    printf("%s %s (%s)\n", info.library_name, info.library_version, info.valid_extensions);
+
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample(audio_sample);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+
+    FILE *f = fopen(argv[2], "rb");
+    if (!f) {
+        perror("fopen");
+        return 1;
+    }
+    static uint8_t rom[4096];
+    size_t rom_size = fread(rom, 1, sizeof(rom), f);
+    fclose(f);
+
+    struct retro_game_info game = {argv[2], rom, rom_size, NULL};
+    CHECK(retro_load_game(&game));
+
+    struct retro_system_av_info av;
+    retro_get_system_av_info(&av);
+    CHECK(av.timing.fps == 60.0);
+
+    for (int i = 0; i < 120; i++) {
+        retro_run();
+    }
+    CHECK(video_frames == 120);
+    CHECK(video_width == av.geometry.base_width && video_height == av.geometry.base_height);
+    CHECK(audio_frames == 120 * (size_t)(av.timing.sample_rate / 60));
+
+    size_t size = retro_serialize_size();
+    void *state = malloc(size);
+    CHECK(retro_serialize(state, size));
+    retro_run();
+    uint32_t expected_hash = video_hash;
+
+    /* Running on and restoring must reproduce the frame after the save. */
+    for (int i = 0; i < 60; i++) {
+        retro_run();
+    }
+    CHECK(retro_unserialize(state, size));
+    retro_run();
+    CHECK(video_hash == expected_hash);
+
+    free(state);
+    retro_unload_game();
+    retro_deinit();
+    dlclose(core);
+    printf("ok: %u frames, last frame hash %08x\n", video_frames, video_hash);
+    return 0;
+}
@@ -0,0 +1,441 @@
+//! A [libretro](https://docs.libretro.com/) core around `Chip8VM`.
+//!
+//! The libretro API is a set of C functions the frontend (RetroArch etc.) calls
+//! into, plus callbacks it registers for video, audio, input and environment
+//! queries. Since the API is global, the core and callbacks live in statics.
+
+use chip8_core::display::Display;
+use chip8_core::palette::Palette;
+use chip8_core::render::{Renderer, Scaler};
+use chip8_core::vm::{Chip8VM, DEFAULT_CYCLES_PER_FRAME};
+use std::ffi::{c_char, c_uint, c_void};
+use std::sync::Mutex;
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: u32 = 44_100;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / 60) as usize;
+const BEEP_HZ: u32 = 440;
+const BEEP_VOLUME: i16 = 4_000;
+// Save states vary slightly in size with the stack depth, so they are stored
+// length-prefixed in a fixed-size buffer as libretro expects.
+const SERIALIZE_SIZE: usize = 8 * 1024;
+
+// RETROK_* codes for the same layout as the desktop frontend:
+// [1, 2, 3, 4]
+// [Q, W, E, R]
+// [A, S, D, F]
+// [Z, X, C, V]
+const KEYBOARD_MAP: [(c_uint, u8); 16] = [
+    (b'1' as c_uint, 0x1),
+    (b'2' as c_uint, 0x2),
+    (b'3' as c_uint, 0x3),
+    (b'4' as c_uint, 0xC),
+    (b'q' as c_uint, 0x4),
+    (b'w' as c_uint, 0x5),
+    (b'e' as c_uint, 0x6),
+    (b'r' as c_uint, 0xD),
+    (b'a' as c_uint, 0x7),
+    (b's' as c_uint, 0x8),
+    (b'd' as c_uint, 0x9),
+    (b'f' as c_uint, 0xE),
+    (b'z' as c_uint, 0xA),
+    (b'x' as c_uint, 0x0),
+    (b'c' as c_uint, 0xB),
+    (b'v' as c_uint, 0xF),
+];
+
+// RETRO_DEVICE_ID_JOYPAD_* to keys. Most games use 2/4/6/8 as a d-pad.
+const JOYPAD_MAP: [(c_uint, u8); 8] = [
+    (4, 0x2), // up
+    (5, 0x8), // down
+    (6, 0x4), // left
+    (7, 0x6), // right
+    (8, 0x5), // A
+    (0, 0x0), // B
+    (9, 0xE), // X
+    (1, 0xF), // Y
+];
+
+type EnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = unsafe extern "C" fn();
+type InputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+struct Core {
+    vm: Chip8VM,
+    rom: Vec<u8>,
+    renderer: Renderer,
+    // XRGB8888 frame handed to video_refresh.
+    video: Vec<u32>,
+    audio: Vec<i16>,
+    audio_phase: u32,
+    keys: [bool; 16],
+    // set by a fault, nothing runs until a reset or a state is loaded.
+    faulted: bool,
+}
+
+impl Core {
+    fn new(rom: Vec<u8>) -> Option<Core> {
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(&rom).ok()?;
+        Some(Core {
+            vm,
+            rom,
+            renderer: Renderer::new(Palette::default(), Scaler::default()),
+            video: vec![0; Display::WIDTH * Display::HEIGHT],
+            audio: vec![0; SAMPLES_PER_FRAME * 2],
+            audio_phase: 0,
+            keys: [false; 16],
+            faulted: false,
+        })
+    }
+
+    fn run_frame(&mut self, callbacks: &Callbacks) {
+        if let Some(input_poll) = callbacks.input_poll {
+            unsafe { input_poll() };
+        }
+        if let Some(input_state) = callbacks.input_state {
+            self.update_keys(input_state);
+        }
+
+        // Faults stop the machine, the frontend keeps showing the last frame.
+        let mut beeping = false;
+        if !self.faulted {
+            for _ in 0..DEFAULT_CYCLES_PER_FRAME {
+                if self.vm.cycle().is_err() {
+                    self.faulted = true;
+                    break;
+                }
+            }
+            beeping = self.vm.sound_timer() > 0;
+            self.vm.tick_timers();
+        }
+
+        let rgba = self.renderer.render(self.vm.get_frame_buffer());
+        for (out, px) in self.video.iter_mut().zip(rgba.chunks_exact(4)) {
+            *out = u32::from_be_bytes([0, px[0], px[1], px[2]]);
+        }
+        if let Some(video_refresh) = callbacks.video_refresh {
+            unsafe {
+                video_refresh(
+                    self.video.as_ptr() as *const c_void,
+                    Display::WIDTH as c_uint,
+                    Display::HEIGHT as c_uint,
+                    Display::WIDTH * 4,
+                )
+            };
+        }
+
+        self.fill_audio(beeping);
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            unsafe { audio_sample_batch(self.audio.as_ptr(), SAMPLES_PER_FRAME) };
+        }
+    }
+
+    fn update_keys(&mut self, input_state: InputStateFn) {
+        let mut pressed = [false; 16];
+        for (id, key) in KEYBOARD_MAP {
+            pressed[key as usize] |= unsafe { input_state(0, RETRO_DEVICE_KEYBOARD, 0, id) } != 0;
+        }
+        for (id, key) in JOYPAD_MAP {
+            pressed[key as usize] |= unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, id) } != 0;
+        }
+        for (key, (now, before)) in pressed.iter().zip(self.keys).enumerate() {
+            if *now != before {
+                self.vm.handle_key(key as u8, *now);
+            }
+        }
+        self.keys = pressed;
+    }
+
+    // Square wave while the sound timer is active.
+    fn fill_audio(&mut self, beeping: bool) {
+        let half_period = SAMPLE_RATE / BEEP_HZ / 2;
+        for frame in self.audio.chunks_exact_mut(2) {
+            let sample = if !beeping {
+                0
+            } else if (self.audio_phase / half_period).is_multiple_of(2) {
+                BEEP_VOLUME
+            } else {
+                -BEEP_VOLUME
+            };
+            self.audio_phase = self.audio_phase.wrapping_add(1);
+            frame[0] = sample;
+            frame[1] = sample;
+        }
+    }
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> std::sync::MutexGuard<'static, Callbacks> {
+    CALLBACKS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn core() -> std::sync::MutexGuard<'static, Option<Core>> {
+    CORE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(cb: EnvironmentFn) {
+    callbacks().environment = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(cb: VideoRefreshFn) {
+    callbacks().video_refresh = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_cb: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(cb: AudioSampleBatchFn) {
+    callbacks().audio_sample_batch = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(cb: InputPollFn) {
+    callbacks().input_poll = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(cb: InputStateFn) {
+    callbacks().input_state = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"chip8-rs".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8|c8".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: Display::WIDTH as c_uint,
+            base_height: Display::HEIGHT as c_uint,
+            max_width: Display::WIDTH as c_uint,
+            max_height: Display::HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: RetroSystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    let mut core = core();
+    if let Some(rom) = core.as_ref().map(|c| c.rom.clone()) {
+        *core = Core::new(rom);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    if let Some(core) = core().as_mut() {
+        core.run_frame(&callbacks);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SERIALIZE_SIZE
+}
+
+/// # Safety
+/// `data` must point to at least `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = core();
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+    let state = core.vm.save_state();
+    if data.is_null() || size < SERIALIZE_SIZE || state.len() + 4 > SERIALIZE_SIZE {
+        return false;
+    }
+    let out = std::slice::from_raw_parts_mut(data as *mut u8, SERIALIZE_SIZE);
+    out.fill(0);
+    out[..4].copy_from_slice(&(state.len() as u32).to_le_bytes());
+    out[4..4 + state.len()].copy_from_slice(&state);
+    true
+}
+
+/// # Safety
+/// `data` must point to at least `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = core();
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+    if data.is_null() || size < 4 {
+        return false;
+    }
+    let input = std::slice::from_raw_parts(data as *const u8, size);
+    let len = u32::from_le_bytes([input[0], input[1], input[2], input[3]]) as usize;
+    match input.get(4..4 + len) {
+        Some(state) => {
+            let loaded = core.vm.load_state(state).is_ok();
+            core.faulted &= !loaded;
+            loaded
+        }
+        None => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be null or point to a valid `retro_game_info` whose `data`
+/// holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size).to_vec();
+
+    if let Some(environment) = callbacks().environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+    }
+
+    let loaded = Core::new(rom);
+    let ok = loaded.is_some();
+    *core() = loaded;
+    ok
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *core() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,28 @@
+"""Mash random keys for a few seconds and report how much of the screen changed.
+
+    maturin develop --release
+    python examples/random_keys.py path/to/rom.ch8
+"""
+import random
+import sys
+
+import chip8
+
+vm = chip8.Chip8()
+with open(sys.argv[1], "rb") as f:
+    vm.load_rom(f.read())
+
+changed = 0
+previous = bytes(vm.framebuffer())
+for frame in range(600):
+    if frame % 10 == 0:
+        key = random.randrange(16)
+        vm.key_down(key)
+    elif frame % 10 == 5:
+        vm.key_up(key)
+    vm.run_frame()
+    current = bytes(vm.framebuffer())
+    changed += sum(a != b for a, b in zip(previous, current))
+    previous = current
+
+print(f"{changed} pixel changes over 600 frames, PC={vm.pc:03X}")
@@ -0,0 +1,165 @@
+//! Python bindings for `chip8_core`, built with `maturin develop --release`.
+//!
+//! ```python
+//! import chip8
+//! import numpy as np
+//!
+//! vm = chip8.Chip8()
+//! vm.load_rom(open("rom.ch8", "rb").read())
+//! vm.run_frame()
+//! screen = np.asarray(vm.framebuffer())  # uint8, shape (32, 64)
+//! ```
+//!
+//! Framebuffer and memory are returned as read-only `memoryview`s over a
+//! snapshot, so `numpy.asarray` wraps them without another copy and they stay
+//! valid while the VM keeps running.
+
+use chip8_core::display::Display;
+use chip8_core::vm::{Chip8VM, VMError, DEFAULT_CYCLES_PER_FRAME};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyMemoryView};
+
+create_exception!(chip8, Chip8Error, PyException, "Raised when the VM faults.");
+
+fn to_py_err(err: VMError) -> PyErr {
+    Chip8Error::new_err(err.to_string())
+}
+
+/// Wrap `data` in a read-only memoryview, reshaped to `shape` if given.
+fn memory_view<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    shape: Option<(usize, usize)>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let view = PyMemoryView::from(&PyBytes::new(py, data))?.into_any();
+    match shape {
+        Some(shape) => view.call_method1("cast", ("B", shape)),
+        None => Ok(view),
+    }
+}
+
+#[pyclass(module = "chip8", name = "Chip8", unsendable)]
+struct PyChip8 {
+    vm: Chip8VM,
+}
+
+#[pymethods]
+impl PyChip8 {
+    #[classattr]
+    const WIDTH: usize = Display::WIDTH;
+    #[classattr]
+    const HEIGHT: usize = Display::HEIGHT;
+
+    #[new]
+    fn new() -> PyChip8 {
+        PyChip8 { vm: Chip8VM::new() }
+    }
+
+    /// Reset the VM and load a ROM image.
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(rom).map_err(to_py_err)?;
+        self.vm = vm;
+        Ok(())
+    }
+
+    /// Execute a single instruction without ticking timers.
+    fn step(&mut self) -> PyResult<()> {
+        self.vm.cycle().map_err(to_py_err)
+    }
+
+    /// Run one 60Hz frame: `cycles` instructions followed by a timer tick.
+    #[pyo3(signature = (cycles = DEFAULT_CYCLES_PER_FRAME))]
+    fn run_frame(&mut self, cycles: u32) -> PyResult<()> {
+        for _ in 0..cycles {
+            self.vm.cycle().map_err(to_py_err)?;
+        }
+        self.vm.tick_timers();
+        Ok(())
+    }
+
+    fn tick_timers(&mut self) {
+        self.vm.tick_timers();
+    }
+
+    /// `key` is the CHIP-8 key value, 0x0 through 0xF.
+    fn key_down(&mut self, key: u8) -> PyResult<()> {
+        self.set_key(key, true)
+    }
+
+    fn key_up(&mut self, key: u8) -> PyResult<()> {
+        self.set_key(key, false)
+    }
+
+    fn set_key(&mut self, key: u8, pressed: bool) -> PyResult<()> {
+        if key > 0xF {
+            return Err(PyValueError::new_err(format!("Unknown key: {}", key)));
+        }
+        self.vm.handle_key(key, pressed);
+        Ok(())
+    }
+
+    /// The display as a (HEIGHT, WIDTH) memoryview of 0/1 bytes.
+    fn framebuffer<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let pixels: Vec<u8> = self
+            .vm
+            .get_frame_buffer()
+            .iter()
+            .map(|&on| on as u8)
+            .collect();
+        memory_view(py, &pixels, Some((Display::HEIGHT, Display::WIDTH)))
+    }
+
+    /// All 4KB of RAM as a flat memoryview.
+    fn memory<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        memory_view(py, self.vm.memory(), None)
+    }
+
+    /// V0 through VF as a 16 byte `bytes`.
+    #[getter]
+    fn registers(&self) -> Vec<u8> {
+        self.vm.registers().to_vec()
+    }
+
+    #[getter]
+    fn pc(&self) -> u16 {
+        self.vm.pc()
+    }
+
+    #[getter]
+    fn i(&self) -> u16 {
+        self.vm.index_register()
+    }
+
+    #[getter]
+    fn delay_timer(&self) -> u8 {
+        self.vm.delay_timer()
+    }
+
+    #[getter]
+    fn sound_timer(&self) -> u8 {
+        self.vm.sound_timer()
+    }
+
+    #[getter]
+    fn stack(&self) -> Vec<u16> {
+        self.vm.call_stack().to_vec()
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.vm.save_state())
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.vm.load_state(state).map_err(to_py_err)
+    }
+}
+
+#[pymodule]
+fn chip8(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyChip8>()?;
+    m.add("Chip8Error", m.py().get_type::<Chip8Error>())?;
+    Ok(())
+}
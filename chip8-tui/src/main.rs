@@ -0,0 +1,435 @@
+use chip8_core::display::Display;
+use chip8_core::palette::Palette;
+use chip8_core::vm::Chip8VM;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::env;
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+const TIMER_INTERVAL: Duration = Duration::from_micros(1_000_000 / 60); // 60Hz
+const CYCLE_INTERVAL: Duration = Duration::from_micros(1_000_000 / 500); // 500Hz
+
+// Terminals without key release reporting only send presses (and repeats),
+// so keys are released after this long without another press. Has to
+// outlast the OS key-repeat delay (usually 250-500ms) or held keys bounce.
+const DEFAULT_KEY_HOLD: Duration = Duration::from_millis(500);
+const PANEL_COLUMN: u16 = Display::WIDTH as u16 + 2;
+const DISASSEMBLY_LINES: u16 = 12;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PixelMode {
+    // 1x2 pixels per cell using ▀ ▄ █
+    HalfBlock,
+    // 2x4 pixels per cell using braille patterns
+    Braille,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let (rom_path, mode, palette, key_hold) = match parse_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(msg) => {
+            println!("{}", msg);
+            println!(
+                "Usage: chip8-tui [--braille] [--palette <name>] [--key-hold <ms>] <path/to/rom.ch8>"
+            );
+            return;
+        }
+    };
+
+    let mut vm = Chip8VM::new();
+    if let Err(e) = vm.load_rom(&rom_path) {
+        println!("Failed to start emulator: {}", e);
+        return;
+    }
+
+    let mut tui = Tui::new(vm, mode, palette, key_hold);
+    if let Err(e) = tui.run() {
+        println!("Terminal error: {}", e);
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(String, PixelMode, Palette, Duration), String> {
+    let mut rom_path = None;
+    let mut mode = PixelMode::HalfBlock;
+    let mut palette = Palette::default();
+    let mut key_hold = DEFAULT_KEY_HOLD;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--braille" => mode = PixelMode::Braille,
+            "--palette" => {
+                let name = iter.next().ok_or("Missing value for --palette")?;
+                palette =
+                    Palette::named(name).ok_or_else(|| format!("Unknown palette: {}", name))?;
+            }
+            "--key-hold" => {
+                let ms = iter.next().ok_or("Missing value for --key-hold")?;
+                let ms = ms
+                    .parse()
+                    .map_err(|_| format!("Invalid --key-hold: {}", ms))?;
+                key_hold = Duration::from_millis(ms);
+            }
+            _ if rom_path.is_none() && !arg.starts_with("--") => rom_path = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let rom_path = rom_path.ok_or("Missing ROM path")?;
+    Ok((rom_path, mode, palette, key_hold))
+}
+
+struct Tui {
+    vm: Chip8VM,
+    mode: PixelMode,
+    palette: Palette,
+    out: Stdout,
+    paused: bool,
+    status: String,
+    // when each key should be released if the terminal can't tell us.
+    held_keys: [Option<Instant>; 16],
+    key_hold: Duration,
+    reports_release: bool,
+    last_cycle: Instant,
+    last_timer_update: Instant,
+}
+
+impl Tui {
+    fn new(vm: Chip8VM, mode: PixelMode, palette: Palette, key_hold: Duration) -> Tui {
+        Tui {
+            vm,
+            mode,
+            palette,
+            out: io::stdout(),
+            paused: false,
+            status: String::new(),
+            held_keys: [None; 16],
+            key_hold,
+            reports_release: false,
+            last_cycle: Instant::now(),
+            last_timer_update: Instant::now(),
+        }
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        execute!(self.out, EnterAlternateScreen, cursor::Hide)?;
+        // Key release events need the kitty keyboard protocol.
+        self.reports_release = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if self.reports_release {
+            execute!(
+                self.out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        let result = self.event_loop();
+
+        if self.reports_release {
+            execute!(self.out, PopKeyboardEnhancementFlags)?;
+        }
+        execute!(self.out, ResetColor, cursor::Show, LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self) -> io::Result<()> {
+        execute!(self.out, terminal::Clear(terminal::ClearType::All))?;
+        loop {
+            while event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    if !self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+            self.release_expired_keys();
+
+            let now = Instant::now();
+            if !self.paused && now.duration_since(self.last_cycle) > CYCLE_INTERVAL {
+                self.step();
+                self.last_cycle = now;
+            }
+
+            if now.duration_since(self.last_timer_update) > TIMER_INTERVAL {
+                if !self.paused {
+                    self.vm.tick_timers();
+                }
+                self.last_timer_update = now;
+                self.draw()?;
+            }
+
+            std::thread::sleep(Duration::from_micros(500));
+        }
+    }
+
+    fn step(&mut self) {
+        if let Err(err) = self.vm.cycle() {
+            self.status = format!("{} (paused)", err);
+            self.paused = true;
+        }
+    }
+
+    // Returns false when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let pressed = key.kind != KeyEventKind::Release;
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::F(5) if pressed => {
+                self.paused = !self.paused;
+                self.status.clear();
+            }
+            KeyCode::F(10) if pressed && self.paused => self.step(),
+            KeyCode::Char(c) => {
+                if let Some(key_code) = map_key(c.to_ascii_lowercase()) {
+                    if pressed {
+                        // Repeats only extend the hold, the VM sees a single press.
+                        let held = self.held_keys[key_code as usize]
+                            .replace(Instant::now() + self.key_hold)
+                            .is_some();
+                        if !held {
+                            self.vm.handle_key(key_code, true);
+                        }
+                    } else {
+                        self.held_keys[key_code as usize] = None;
+                        self.vm.handle_key(key_code, false);
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn release_expired_keys(&mut self) {
+        if self.reports_release {
+            return;
+        }
+        let now = Instant::now();
+        for key_code in 0..16 {
+            if matches!(self.held_keys[key_code], Some(deadline) if deadline <= now) {
+                self.held_keys[key_code] = None;
+                self.vm.handle_key(key_code as u8, false);
+            }
+        }
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let [fr, fg, fb, _] = self.palette.foreground();
+        let [br, bg, bb, _] = self.palette.background();
+        queue!(
+            self.out,
+            SetForegroundColor(Color::Rgb {
+                r: fr,
+                g: fg,
+                b: fb
+            }),
+            SetBackgroundColor(Color::Rgb {
+                r: br,
+                g: bg,
+                b: bb
+            })
+        )?;
+        let lines = match self.mode {
+            PixelMode::HalfBlock => half_block_lines(self.vm.get_frame_buffer()),
+            PixelMode::Braille => braille_lines(self.vm.get_frame_buffer()),
+        };
+        for (row, line) in lines.iter().enumerate() {
+            queue!(self.out, cursor::MoveTo(0, row as u16), Print(line))?;
+        }
+        queue!(self.out, ResetColor)?;
+        self.draw_panel()?;
+        self.out.flush()
+    }
+
+    fn draw_panel(&mut self) -> io::Result<()> {
+        let mut lines = Vec::new();
+        let regs = self.vm.registers();
+        for row in 0..4 {
+            let cols: Vec<String> = (0..4)
+                .map(|col| row * 4 + col)
+                .map(|r| format!("V{:X}={:02X}", r, regs[r]))
+                .collect();
+            lines.push(cols.join(" "));
+        }
+        lines.push(format!(
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+            self.vm.pc(),
+            self.vm.index_register(),
+            self.vm.delay_timer(),
+            self.vm.sound_timer()
+        ));
+        let stack: Vec<String> = self
+            .vm
+            .call_stack()
+            .iter()
+            .map(|a| format!("{:03X}", a))
+            .collect();
+        lines.push(format!("Stack: {}", stack.join(" ")));
+        lines.push(String::new());
+
+        // Disassemble a window around PC, keeping PC's alignment.
+        let pc = self.vm.pc();
+        let start = pc.saturating_sub(2 * (DISASSEMBLY_LINES / 3));
+        for i in 0..DISASSEMBLY_LINES {
+            let addr = start + i * 2;
+            let marker = if addr == pc { ">" } else { " " };
+            lines.push(format!(
+                "{} {:03X}  {}",
+                marker,
+                addr,
+                self.vm.instruction_at(addr)
+            ));
+        }
+        lines.push(String::new());
+
+        let state = if self.paused {
+            "PAUSED  F5 resume  F10 step"
+        } else if self.vm.is_waiting_for_key() {
+            "WAITING FOR KEY  F5 pause"
+        } else {
+            "RUNNING  F5 pause"
+        };
+        lines.push(format!("{}  Esc quit", state));
+        lines.push(self.status.clone());
+
+        for (row, line) in lines.iter().enumerate() {
+            queue!(
+                self.out,
+                cursor::MoveTo(PANEL_COLUMN, row as u16),
+                terminal::Clear(terminal::ClearType::UntilNewLine),
+                Print(line)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn half_block_lines(frame: &[bool]) -> Vec<String> {
+    let px = |x: usize, y: usize| frame[y * Display::WIDTH + x];
+    (0..Display::HEIGHT)
+        .step_by(2)
+        .map(|y| {
+            (0..Display::WIDTH)
+                .map(|x| match (px(x, y), px(x, y + 1)) {
+                    (false, false) => ' ',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (true, true) => '█',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn braille_lines(frame: &[bool]) -> Vec<String> {
+    // Dot bit for each (x, y) position within a 2x4 braille cell.
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let px = |x: usize, y: usize| frame[y * Display::WIDTH + x];
+    (0..Display::HEIGHT)
+        .step_by(4)
+        .map(|y| {
+            (0..Display::WIDTH)
+                .step_by(2)
+                .map(|x| {
+                    let mut bits = 0;
+                    for (dy, row) in DOTS.iter().enumerate() {
+                        for (dx, bit) in row.iter().enumerate() {
+                            if px(x + dx, y + dy) {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap_or(' ')
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Same layout as the chip8 frontend:
+// [1, 2, 3, 4]
+// [Q, W, E, R]
+// [A, S, D, F]
+// [Z, X, C, V]
+fn map_key(c: char) -> Option<u8> {
+    match c {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with(pixels: &[(usize, usize)]) -> Vec<bool> {
+        let mut frame = vec![false; Display::WIDTH * Display::HEIGHT];
+        for (x, y) in pixels {
+            frame[y * Display::WIDTH + x] = true;
+        }
+        frame
+    }
+
+    #[test]
+    fn test_half_block_lines() {
+        let lines = half_block_lines(&frame_with(&[(0, 0), (1, 1), (2, 0), (2, 1), (5, 31)]));
+        assert_eq!(lines.len(), Display::HEIGHT / 2);
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() == Display::WIDTH));
+        assert!(lines[0].starts_with("▀▄█ "));
+        assert_eq!(lines[15].chars().nth(5), Some('▄'));
+    }
+
+    #[test]
+    fn test_braille_lines() {
+        let lines = braille_lines(&frame_with(&[
+            (0, 0),
+            (1, 3),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (2, 3),
+        ]));
+        assert_eq!(lines.len(), Display::HEIGHT / 4);
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() == Display::WIDTH / 2));
+        // Top left and bottom right dots, then the whole left column.
+        assert!(lines[0].starts_with("\u{2881}\u{2847}\u{2800}"));
+        assert!(lines[7].chars().all(|c| c == '\u{2800}'));
+    }
+
+    #[test]
+    fn test_parse_key_hold() {
+        let args = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        let (_, _, _, hold) = parse_args(&args("rom.ch8")).unwrap();
+        assert_eq!(hold, DEFAULT_KEY_HOLD);
+        let (_, _, _, hold) = parse_args(&args("--key-hold 800 rom.ch8")).unwrap();
+        assert_eq!(hold, Duration::from_millis(800));
+        assert!(parse_args(&args("--key-hold soon rom.ch8")).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! JavaScript bindings for `chip8_core`, built with `wasm-pack build --target web`.
+//!
+//! ```js
+//! import init, { Emulator } from "./pkg/chip8_wasm.js";
+//! const wasm = await init();
+//! const emu = new Emulator();
+//! emu.load_rom(new Uint8Array(await (await fetch("rom.ch8")).arrayBuffer()));
+//! // every animation frame:
+//! emu.run_frame();
+//! if (emu.frame_changed()) {
+//!   const rgba = new Uint8ClampedArray(wasm.memory.buffer, emu.frame_ptr(), emu.frame_len());
+//!   ctx.putImageData(new ImageData(rgba, emu.width(), emu.height()), 0, 0);
+//! }
+//! ```
+
+use chip8_core::palette::Palette;
+use chip8_core::render::{Renderer, Scaler};
+use chip8_core::vm::{Chip8VM, DEFAULT_CYCLES_PER_FRAME};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Emulator {
+    vm: Chip8VM,
+    renderer: Renderer,
+    frame: Vec<u8>,
+    frame_changed: bool,
+    cycles_per_frame: u32,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Emulator {
+        let mut emu = Emulator {
+            vm: Chip8VM::new(),
+            renderer: Renderer::new(Palette::default(), Scaler::default()),
+            frame: Vec::new(),
+            frame_changed: false,
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+        };
+        emu.render();
+        emu
+    }
+
+    /// Start a fresh VM with the given ROM image.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        let mut vm = Chip8VM::new();
+        vm.load_rom_bytes(rom)?;
+        self.vm = vm;
+        self.render();
+        Ok(())
+    }
+
+    /// Run one 60Hz frame: a batch of CPU cycles, a timer tick and a redraw
+    /// if the display changed.
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        for _ in 0..self.cycles_per_frame {
+            self.vm.cycle()?;
+        }
+        self.vm.tick_timers();
+        self.render_if_dirty();
+        Ok(())
+    }
+
+    /// Execute a single instruction without ticking timers.
+    pub fn step(&mut self) -> Result<(), JsError> {
+        self.vm.cycle()?;
+        self.render_if_dirty();
+        Ok(())
+    }
+
+    /// Whether the frame changed since the last call, so callers can skip
+    /// `putImageData` when nothing was drawn.
+    pub fn frame_changed(&mut self) -> bool {
+        core::mem::take(&mut self.frame_changed)
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles: u32) {
+        self.cycles_per_frame = cycles;
+    }
+
+    /// `key` is the CHIP-8 key value, 0x0 through 0xF.
+    pub fn key_down(&mut self, key: u8) {
+        self.vm.handle_key(key, true);
+    }
+
+    pub fn key_up(&mut self, key: u8) {
+        self.vm.handle_key(key, false);
+    }
+
+    /// Whether the sound timer is running, i.e. the buzzer should be on.
+    pub fn sound_active(&self) -> bool {
+        self.vm.sound_timer() > 0
+    }
+
+    /// Switch to a built-in palette by name. Returns false for unknown names.
+    pub fn set_palette(&mut self, name: &str) -> bool {
+        match Palette::named(name) {
+            Some(palette) => {
+                self.renderer.palette = palette;
+                self.render();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.renderer.size().0
+    }
+
+    pub fn height(&self) -> usize {
+        self.renderer.size().1
+    }
+
+    /// Pointer to the RGBA frame in wasm memory, for a zero-copy
+    /// `Uint8ClampedArray` view. Views are invalidated if wasm memory grows.
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.frame.as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+
+    /// A copy of the RGBA frame, for callers that don't want to manage views.
+    pub fn frame(&self) -> Vec<u8> {
+        self.frame.clone()
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        self.vm.save_state()
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsError> {
+        self.vm.load_state(state)?;
+        self.render();
+        Ok(())
+    }
+
+    fn render(&mut self) {
+        let rgba = self.renderer.render(self.vm.get_frame_buffer());
+        self.frame.clear();
+        self.frame.extend_from_slice(rgba);
+        self.frame_changed = true;
+        self.vm.clear_display_dirty();
+    }
+
+    fn render_if_dirty(&mut self) {
+        if self.vm.display().is_dirty() {
+            self.render();
+        }
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Emulator::new()
+    }
+}
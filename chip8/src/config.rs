@@ -0,0 +1,412 @@
+//! Command line options and TOML config files.
+//!
+//! Settings are layered, later layers winning: the ROM database and cart
+//! options, the global config file, its `[rom."<name or sha1>"]` table for the
+//! ROM, a `<rom>.toml` next to the ROM, then the command line.
+//!
+//! ```toml
+//! speed = 700
+//! scale = 10
+//! palette = "amber"
+//! log-level = "warn"
+//!
+//! [quirks]
+//! vblank = false
+//!
+//! [keymap]
+//! ArrowUp = 0x5
+//!
+//! [rom."pong.ch8"]
+//! speed = 300
+//! ```
+
+use chip8_core::palette::Palette;
+use chip8_core::platform::Platform;
+use chip8_core::quirks::Quirks;
+use chip8_core::rom::RomInfo;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "chip8/config.toml";
+
+#[derive(Parser)]
+#[command(version, about = "A CHIP-8 emulator")]
+pub struct Cli {
+    /// ROM to run, a .ch8 file or an Octo cart (.gif)
+    pub rom: String,
+    /// Config file to read instead of ~/.config/chip8/config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Ignore config files
+    #[arg(long)]
+    pub no_config: bool,
+    /// CPU speed in instructions per second [default: 500]
+    #[arg(long, value_name = "HZ")]
+    pub speed: Option<u32>,
+    /// Window size as a multiple of 64x32 [default: 8]
+    #[arg(long)]
+    pub scale: Option<u32>,
+    #[arg(long, value_name = "NAME")]
+    pub palette: Option<String>,
+    /// Foreground color
+    #[arg(long, value_name = "#RRGGBB")]
+    pub fg: Option<String>,
+    /// Background color
+    #[arg(long, value_name = "#RRGGBB")]
+    pub bg: Option<String>,
+    #[arg(long, value_name = "MODE")]
+    pub persistence: Option<String>,
+    #[arg(long, value_name = "NAME")]
+    pub scaler: Option<String>,
+    #[arg(long, value_name = "NAME")]
+    pub platform: Option<String>,
+    /// Override a quirk, e.g. --quirk wrap=false. Repeatable
+    #[arg(long = "quirk", value_name = "NAME=BOOL", value_parser = parse_quirk)]
+    pub quirks: Vec<(String, bool)>,
+    /// Bind a keyboard key to a CHIP-8 key, e.g. --key ArrowUp=5. Repeatable
+    #[arg(long = "key", value_name = "KEY=HEX", value_parser = parse_key_binding)]
+    pub keys: Vec<(String, u8)>,
+    /// Log file [default: chip8-debug.log]
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+    /// off, error, warn, info, debug or trace [default: info]
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Start paused, F5 resumes
+    #[arg(long)]
+    pub paused: bool,
+    /// Seed for the random number generator, for repeatable runs
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// A chip-8-database programs.json to use on top of the bundled one
+    #[arg(long, value_name = "FILE")]
+    pub rom_db: Option<String>,
+    /// Don't look ROMs up in the ROM database
+    #[arg(long)]
+    pub no_rom_db: bool,
+}
+
+impl Cli {
+    /// Parse the command line, exiting with usage on errors.
+    pub fn parse_with_help() -> Cli {
+        let help = format!(
+            "Palettes: {}\n\
+             Persistence modes: off, blend, decay[:percent], hold[:frames]\n\
+             Scalers: nearest[:n], scale2x (alias epx), scale3x\n\
+             Platforms: {}\n\
+             Quirks: {}\n\
+             Keys are winit key codes, e.g. KeyW, Digit1, ArrowUp, Space, Numpad5",
+            Palette::names().collect::<Vec<_>>().join(", "),
+            Platform::ALL.map(|p| p.name()).join(", "),
+            Quirks::NAMES.join(", ")
+        );
+        let matches = Cli::command().after_help(help).get_matches();
+        Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            speed: self.speed,
+            scale: self.scale,
+            palette: self.palette.clone(),
+            fg: self.fg.clone(),
+            bg: self.bg.clone(),
+            persistence: self.persistence.clone(),
+            scaler: self.scaler.clone(),
+            platform: self.platform.clone(),
+            quirks: self.quirks.iter().cloned().collect(),
+            keymap: self.keys.iter().cloned().collect(),
+            log_file: self.log_file.clone(),
+            log_level: self.log_level.clone(),
+            fullscreen: self.fullscreen.then_some(true),
+            paused: self.paused.then_some(true),
+            seed: self.seed,
+        }
+    }
+}
+
+/// One layer of settings. Unset values fall through to earlier layers.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub speed: Option<u32>,
+    pub scale: Option<u32>,
+    pub palette: Option<String>,
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub persistence: Option<String>,
+    pub scaler: Option<String>,
+    pub platform: Option<String>,
+    pub quirks: HashMap<String, bool>,
+    /// Keyboard key names to CHIP-8 keys.
+    pub keymap: HashMap<String, u8>,
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<String>,
+    pub fullscreen: Option<bool>,
+    pub paused: Option<bool>,
+    pub seed: Option<u64>,
+}
+
+impl Settings {
+    /// Layer `other` on top of these settings.
+    fn merge(&mut self, other: Settings) {
+        macro_rules! take {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field;
+                })*
+            };
+        }
+        take!(
+            speed,
+            scale,
+            palette,
+            fg,
+            bg,
+            persistence,
+            scaler,
+            platform,
+            log_file,
+            log_level,
+            fullscreen,
+            paused,
+            seed
+        );
+        self.quirks.extend(other.quirks);
+        self.keymap.extend(other.keymap);
+    }
+
+    /// Apply the quirk overrides on top of `quirks`.
+    pub fn apply_quirks(&self, mut quirks: Quirks) -> Result<Quirks, String> {
+        for (name, value) in &self.quirks {
+            *quirks
+                .get_mut(name)
+                .ok_or_else(|| format!("Unknown quirk: {}", name))? = *value;
+        }
+        Ok(quirks)
+    }
+}
+
+/// Merge the config files and command line for the ROM at `rom_path`.
+pub fn load(cli: &Cli, rom_path: &Path, rom: &RomInfo) -> Result<Settings, String> {
+    let mut settings = Settings::default();
+    if !cli.no_config {
+        let global = match &cli.config {
+            Some(path) => Some(path.clone()),
+            // Only read the default location if it exists.
+            None => default_config_path().filter(|path| path.exists()),
+        };
+        if let Some(path) = global {
+            let mut table = read_table(&path)?;
+            let mut roms = match table.remove("rom") {
+                Some(toml::Value::Table(roms)) => roms,
+                Some(_) => return Err(format!("{}: rom must be a table", path.display())),
+                None => toml::Table::new(),
+            };
+            settings.merge(parse_settings(&path, table)?);
+
+            // Tables for the ROM, by file name then by hash.
+            let keys = [
+                rom_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned()),
+                Some(rom.sha1_hex().to_string()),
+            ];
+            for key in keys.into_iter().flatten() {
+                match roms.remove(&key) {
+                    Some(toml::Value::Table(table)) => {
+                        settings.merge(parse_settings(&path, table)?)
+                    }
+                    Some(_) => {
+                        return Err(format!(
+                            "{}: rom.\"{}\" must be a table",
+                            path.display(),
+                            key
+                        ))
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let sidecar = rom_path.with_extension("toml");
+        if sidecar.exists() {
+            let table = read_table(&sidecar)?;
+            settings.merge(parse_settings(&sidecar, table)?);
+        }
+    }
+    settings.merge(cli.settings());
+    Ok(settings)
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join(CONFIG_FILE))
+}
+
+fn read_table(path: &Path) -> Result<toml::Table, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    text.parse()
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_settings(path: &Path, table: toml::Table) -> Result<Settings, String> {
+    Settings::deserialize(table).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_quirk(s: &str) -> Result<(String, bool), String> {
+    let (name, value) = s.split_once('=').ok_or("expected NAME=BOOL")?;
+    if !Quirks::NAMES.contains(&name) {
+        return Err(format!("unknown quirk {}", name));
+    }
+    let value = match value {
+        "true" | "1" => true,
+        "false" | "0" => false,
+        _ => return Err(format!("expected true or false, got {}", value)),
+    };
+    Ok((name.to_string(), value))
+}
+
+fn parse_key_binding(s: &str) -> Result<(String, u8), String> {
+    let (name, key) = s.split_once('=').ok_or("expected KEY=HEX")?;
+    let key = u8::from_str_radix(key.trim_start_matches("0x"), 16)
+        .ok()
+        .filter(|k| *k <= 0xF)
+        .ok_or_else(|| format!("{} isn't a CHIP-8 key 0-F", key))?;
+    Ok((name.to_string(), key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    // A fresh directory per test holding game.ch8, so tests can run in
+    // parallel.
+    fn rom_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("chip8-config-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("game.ch8"), ROM).unwrap();
+        dir
+    }
+
+    fn load_with(dir: &Path, args: &[&str]) -> Result<Settings, String> {
+        let rom_path = dir.join("game.ch8");
+        let config = dir.join("config.toml");
+        let mut argv = vec![
+            "chip8",
+            rom_path.to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+        ];
+        argv.extend_from_slice(args);
+        let cli = Cli::try_parse_from(argv).unwrap();
+        load(&cli, &rom_path, &RomInfo::new(&ROM))
+    }
+
+    #[test]
+    fn test_precedence() {
+        let dir = rom_dir("precedence");
+        let sha1 = RomInfo::new(&ROM).sha1_hex().to_string();
+        let config = format!(
+            r#"
+            seed = 1
+            fg = "1"
+            palette = "1"
+            scale = 1
+            speed = 1
+
+            [quirks]
+            wrap = true
+            jump = true
+
+            [rom."game.ch8"]
+            fg = "2"
+            palette = "2"
+            scale = 2
+            speed = 2
+
+            [rom."{}"]
+            palette = "3"
+            scale = 3
+            speed = 3
+
+            [rom."other.ch8"]
+            seed = 9
+            "#,
+            sha1
+        );
+        fs::write(dir.join("config.toml"), config).unwrap();
+        fs::write(
+            dir.join("game.toml"),
+            "scale = 4\nspeed = 4\n[quirks]\nwrap = false\n",
+        )
+        .unwrap();
+
+        let settings = load_with(&dir, &["--speed", "5"]).unwrap();
+        assert_eq!(settings.seed, Some(1));
+        assert_eq!(settings.fg.as_deref(), Some("2"));
+        assert_eq!(settings.palette.as_deref(), Some("3"));
+        assert_eq!(settings.scale, Some(4));
+        assert_eq!(settings.speed, Some(5));
+        assert!(!settings.quirks["wrap"]);
+        assert!(settings.quirks["jump"]);
+
+        // --no-config leaves only the command line.
+        let settings = load_with(&dir, &["--no-config"]).unwrap();
+        assert_eq!(settings.speed, None);
+        assert!(settings.quirks.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let dir = rom_dir("unknown-keys");
+        fs::write(dir.join("config.toml"), "colour = \"red\"\n").unwrap();
+        let err = load_with(&dir, &[]).unwrap_err();
+        assert!(err.contains("colour"), "{}", err);
+
+        fs::write(dir.join("config.toml"), "[rom.\"game.ch8\"]\nfast = true\n").unwrap();
+        let err = load_with(&dir, &[]).unwrap_err();
+        assert!(err.contains("fast"), "{}", err);
+
+        fs::write(dir.join("config.toml"), "rom = 5\n").unwrap();
+        assert!(load_with(&dir, &[]).is_err());
+
+        fs::write(dir.join("config.toml"), "").unwrap();
+        fs::write(dir.join("game.toml"), "sped = 5\n").unwrap();
+        let err = load_with(&dir, &[]).unwrap_err();
+        assert!(err.contains("game.toml"), "{}", err);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_quirks() {
+        assert!(Cli::try_parse_from(["chip8", "game.ch8", "--quirk", "warp=1"]).is_err());
+        assert!(Cli::try_parse_from(["chip8", "game.ch8", "--quirk", "wrap=yes"]).is_err());
+        assert!(Cli::try_parse_from(["chip8", "game.ch8", "--quirk", "wrap=1"]).is_ok());
+
+        let dir = rom_dir("unknown-quirks");
+        fs::write(dir.join("config.toml"), "[quirks]\nwarp = true\n").unwrap();
+        let settings = load_with(&dir, &[]).unwrap();
+        let err = settings.apply_quirks(Quirks::default()).unwrap_err();
+        assert_eq!(err, "Unknown quirk: warp");
+
+        fs::write(dir.join("config.toml"), "[quirks]\nvblank = false\n").unwrap();
+        let settings = load_with(&dir, &[]).unwrap();
+        let quirks = settings.apply_quirks(Quirks::default()).unwrap();
+        assert!(!quirks.vblank);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,128 +1,448 @@
+mod config;
+mod romdb;
+
+use chip8_core::cart::{CartOptions, Cartridge};
 use chip8_core::display::Display;
-use chip8_core::vm::{Chip8VM, VMError};
+use chip8_core::events::Event;
+use chip8_core::palette::{parse_color, Palette};
+use chip8_core::persistence::{Persistence, PersistenceMode};
+use chip8_core::platform::Platform;
+use chip8_core::render::{Renderer, Scaler};
+use chip8_core::rom::RomInfo;
+use chip8_core::vm::{Chip8VM, RomLoadError, VMError};
+use config::{Cli, Settings};
+use log::info;
 use pixels::{Pixels, SurfaceTexture};
-use simplelog;
-use std::fs::File;
-use std::path::Path;
+use romdb::RomDatabase;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use std::{env, sync::Arc};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
 use winit::event::WindowEvent;
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::keyboard::{KeyCode, PhysicalKey};
-use winit::window::Window;
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::window::{Fullscreen, Window};
 
-const WINDOW_WIDTH: u32 = 512;
-const WINDOW_HEIGHT: u32 = 256;
+// The window is the 64x32 display times this unless configured.
+const DEFAULT_SCALE: u32 = 8;
+// Instructions per second unless the ROM database or config say otherwise.
+const DEFAULT_SPEED: u32 = 500;
 const TIMER_INTERVAL: Duration = Duration::from_micros(1_000_000 / 60); // 60Hz
-const CYCLE_INTERVAL: Duration = Duration::from_micros(1_000_000 / 500); // 500Hz
-const LOG_FILE: &str = "chip8-debug.log";
+const DEFAULT_LOG_FILE: &str = "chip8-debug.log";
+// Speed multipliers F7/F8 step through, scaling both cycles and timers.
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 1.5, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+// Extra multiplier while Tab is held.
+const FAST_FORWARD: f64 = 4.0;
+// Shown by the open ROM dialog.
+const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "sc8", "xo8", "gif"];
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: chip8 <path/to/rom.ch8>");
-        return;
+    let cli = Cli::parse_with_help();
+    if let Err(e) = run(cli) {
+        println!("Failed to start emulator: {}", e);
     }
+}
 
-    let log_file = File::create(LOG_FILE).unwrap();
-    simplelog::CombinedLogger::init(vec![simplelog::WriteLogger::new(
-        // Set to debug to get full instruction logging.
-        simplelog::LevelFilter::Info,
-        simplelog::Config::default(),
-        log_file,
-    )])
-    .unwrap();
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let (rom, cart_options) = read_program(&cli.rom)?;
+    let rom_info = RomInfo::new(&rom);
+    let settings = config::load(&cli, Path::new(&cli.rom), &rom_info)?;
 
-    let rom_path = args.get(1).unwrap();
-    match Emulator::new(rom_path.to_string()) {
-        Ok(mut emu) => {
-            let event_loop: EventLoop<()> = EventLoop::new().unwrap();
-            event_loop.set_control_flow(ControlFlow::Poll);
-            let _ = event_loop.run_app(&mut emu);
-        }
-        Err(e) => {
-            println!("Failed to start emulator: {}", e)
-        }
+    // Set the log level to debug to get full instruction logging.
+    let level = match &settings.log_level {
+        Some(level) => simplelog::LevelFilter::from_str(level)
+            .map_err(|_| format!("Unknown log level: {}", level))?,
+        None => simplelog::LevelFilter::Info,
+    };
+    if level != simplelog::LevelFilter::Off {
+        let path = settings
+            .log_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_FILE));
+        let log_file = File::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        simplelog::CombinedLogger::init(vec![simplelog::WriteLogger::new(
+            level,
+            simplelog::Config::default(),
+            log_file,
+        )])?;
     }
+
+    let rom_db = if cli.no_rom_db {
+        RomDatabase::default()
+    } else {
+        let mut db = RomDatabase::bundled();
+        if let Some(path) = &cli.rom_db {
+            let loaded = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| db.add_json(&json).map_err(|e| e.to_string()));
+            if let Err(e) = loaded {
+                return Err(format!("Failed to load ROM database {}: {}", path, e).into());
+            }
+        }
+        db
+    };
+
+    let rom_path = PathBuf::from(&cli.rom);
+    let mut emu = Emulator::new(
+        Rc::new(cli),
+        Rc::new(rom_db),
+        rom_path,
+        &rom,
+        cart_options,
+        &settings,
+    )?;
+    let event_loop: EventLoop<()> = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+    event_loop.run_app(&mut emu)?;
+    Ok(())
 }
 
 struct Emulator {
+    // kept to load other ROMs the same way as the first.
+    cli: Rc<Cli>,
+    rom_db: Rc<RomDatabase>,
     vm: Chip8VM,
-    rom_name: String,
+    rom_path: PathBuf,
+    renderer: Renderer,
+    persistence: Persistence,
+    // persistence keeps fading pixels after the VM stops drawing.
+    always_redraw: bool,
+    // set from VM events, cleared once the frame is drawn.
+    display_changed: bool,
     window: Option<Arc<Window>>,
     frame_buffer: Option<Pixels<'static>>,
-    // manage cycle and timer iterations independently
-    last_cycle: Instant,
-    last_timer_update: Instant,
+    // extra bindings from the ROM database, e.g. arrow keys
+    key_bindings: HashMap<KeyCode, u8>,
+    cycle_interval: Duration,
+    scale: u32,
+    fullscreen: bool,
+    // F5 toggles, nothing runs while paused.
+    paused: bool,
+    speed_index: usize,
+    fast_forward: bool,
+    // Emulated time, which runs at the speed multiplier and stops while
+    // paused. Cycles and timer ticks are scheduled against it so they stay
+    // in step at any speed.
+    clock: Duration,
+    next_cycle: Duration,
+    next_tick: Duration,
+    last_update: Instant,
+    last_redraw: Instant,
+    // for Ctrl+O
+    modifiers: ModifiersState,
 }
 
 impl Emulator {
-    fn new(rom_path: String) -> Result<Self, VMError> {
+    fn new(
+        cli: Rc<Cli>,
+        rom_db: Rc<RomDatabase>,
+        rom_path: PathBuf,
+        rom: &[u8],
+        cart_options: Option<CartOptions>,
+        settings: &Settings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let db_settings = rom_db.lookup(&RomInfo::new(rom));
+
         let mut vm = Chip8VM::new();
-        vm.load_rom(&rom_path)?;
-        let file_name = Path::new(rom_path.as_str())
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned();
+        let mut speed = DEFAULT_SPEED;
+        let mut palette = Palette::default();
+        let mut key_bindings = HashMap::new();
+        if let Some(db_settings) = db_settings {
+            println!("{} ({})", db_settings.title, db_settings.platform);
+            if let Some(description) = &db_settings.description {
+                println!("{}", description);
+            }
+            vm.set_platform(db_settings.platform)?;
+            vm.set_quirks(db_settings.quirks);
+            // The database counts instructions per 60Hz frame.
+            speed = db_settings.tickrate.map_or(speed, |t| t * 60);
+            palette = db_settings.palette.unwrap_or(palette);
+            for (name, key) in &db_settings.keys {
+                if let Some(code) = binding_key_code(name) {
+                    key_bindings.insert(code, *key);
+                }
+            }
+        }
+        // A cart's own options describe how its author ran it.
+        if let Some(cart_options) = cart_options {
+            cart_options.apply(&mut vm);
+            speed = cart_options.tickrate.map_or(speed, |t| t * 60);
+            palette = cart_options.palette.unwrap_or(palette);
+        }
+
+        // Config files and the command line win over both.
+        if let Some(name) = &settings.platform {
+            let platform =
+                Platform::parse(name).ok_or_else(|| format!("Unknown platform: {}", name))?;
+            vm.set_platform(platform)?;
+            vm.set_quirks(platform.quirks());
+        }
+        vm.set_quirks(settings.apply_quirks(vm.quirks())?);
+        if let Some(seed) = settings.seed {
+            vm.seed_rng(seed);
+        }
+        vm.load_rom_bytes(rom)?;
+
+        speed = settings.speed.unwrap_or(speed);
+        if let Some(name) = &settings.palette {
+            palette = Palette::named(name).ok_or_else(|| format!("Unknown palette: {}", name))?;
+        }
+        if let Some(fg) = &settings.fg {
+            palette = palette
+                .with_foreground(parse_color(fg).ok_or_else(|| format!("Invalid color: {}", fg))?);
+        }
+        if let Some(bg) = &settings.bg {
+            palette = palette
+                .with_background(parse_color(bg).ok_or_else(|| format!("Invalid color: {}", bg))?);
+        }
+        let persistence = match &settings.persistence {
+            Some(mode) => PersistenceMode::parse(mode)
+                .ok_or_else(|| format!("Unknown persistence mode: {}", mode))?,
+            None => PersistenceMode::default(),
+        };
+        let scaler = match &settings.scaler {
+            Some(name) => Scaler::parse(name).ok_or_else(|| format!("Unknown scaler: {}", name))?,
+            None => Scaler::default(),
+        };
+        for (name, key) in &settings.keymap {
+            let code = key_code(name).ok_or_else(|| format!("Unknown key: {}", name))?;
+            if *key > 0xF {
+                return Err(format!("{} isn't a CHIP-8 key 0-F", key).into());
+            }
+            key_bindings.insert(code, *key);
+        }
+
         Ok(Self {
-            vm: vm,
-            rom_name: file_name,
+            cli,
+            rom_db,
+            vm,
+            rom_path,
+            renderer: Renderer::new(palette, scaler),
+            persistence: Persistence::new(persistence),
+            always_redraw: persistence != PersistenceMode::Off,
+            display_changed: true,
             window: None,
             frame_buffer: None,
-            last_cycle: Instant::now(),
-            last_timer_update: Instant::now(),
+            key_bindings,
+            cycle_interval: Duration::from_secs(1) / speed.max(1),
+            scale: settings.scale.unwrap_or(DEFAULT_SCALE).max(1),
+            fullscreen: settings.fullscreen.unwrap_or(false),
+            paused: settings.paused.unwrap_or(false),
+            speed_index: NORMAL_SPEED,
+            fast_forward: false,
+            clock: Duration::ZERO,
+            next_cycle: Duration::ZERO,
+            next_tick: TIMER_INTERVAL,
+            last_update: Instant::now(),
+            last_redraw: Instant::now(),
+            modifiers: ModifiersState::empty(),
         })
     }
 
+    /// Swap in the ROM at `path`, looked up and configured like the one given
+    /// on the command line. The window, speed and pause state are kept, and so
+    /// is the running game if the new one fails to load.
+    fn open(&mut self, path: PathBuf) -> Result<(), Box<dyn Error>> {
+        let (rom, cart_options) = read_program(&path.to_string_lossy())?;
+        let settings = config::load(&self.cli, &path, &RomInfo::new(&rom))?;
+        let mut emu = Emulator::new(
+            self.cli.clone(),
+            self.rom_db.clone(),
+            path,
+            &rom,
+            cart_options,
+            &settings,
+        )?;
+        emu.window = self.window.take();
+        emu.frame_buffer = self.frame_buffer.take();
+        emu.paused = self.paused;
+        emu.speed_index = self.speed_index;
+        emu.modifiers = self.modifiers;
+        *self = emu;
+
+        // The ROM's config may pick a scaler with a different output size.
+        if let Some(pixels) = &mut self.frame_buffer {
+            let (width, height) = self.renderer.size();
+            pixels.resize_buffer(width as u32, height as u32)?;
+        }
+        self.update_title();
+        self.request_redraw();
+        Ok(())
+    }
+
+    fn open_or_report(&mut self, path: PathBuf) {
+        let name = path.display().to_string();
+        if let Err(e) = self.open(path) {
+            println!("Failed to open {}: {}", name, e);
+        }
+    }
+
+    /// Ask for a ROM to open, starting in the current ROM's directory.
+    fn pick_rom(&self) -> Option<PathBuf> {
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Open ROM")
+            .add_filter("CHIP-8 ROMs", &ROM_EXTENSIONS);
+        if let Some(dir) = self.rom_path.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(window) = &self.window {
+            dialog = dialog.set_parent(window.as_ref());
+        }
+        dialog.pick_file()
+    }
+
+    /// Soft reset, the ROM stays in memory as the game left it.
+    fn reset(&mut self) {
+        self.vm.reset();
+        self.handle_events();
+        self.request_redraw();
+    }
+
     fn cycle(&mut self) -> Result<(), VMError> {
         let now = Instant::now();
-        if now.duration_since(self.last_cycle) > CYCLE_INTERVAL {
-            self.vm.cycle()?;
-            self.last_cycle = now;
+        // Never catch up more than a frame's worth after a stall.
+        let elapsed = now.duration_since(self.last_update).min(TIMER_INTERVAL);
+        self.last_update = now;
+        if !self.paused {
+            self.clock += elapsed.mul_f64(self.speed());
+            self.run_until_clock()?;
         }
 
-        if now.duration_since(self.last_timer_update) > TIMER_INTERVAL {
-            self.vm.tick_timers();
-            self.last_timer_update = now;
+        // Redraw at 60hz, only when something changed.
+        if now.duration_since(self.last_redraw) > TIMER_INTERVAL {
+            self.last_redraw = now;
+            if self.display_changed || self.always_redraw {
+                self.request_redraw();
+            }
+        }
+        Ok(())
+    }
 
-            // Redraw at the timer frequency of 60hz
-            if let Some(window) = &self.window {
-                window.request_redraw();
+    // Run every cycle and timer tick due by the emulated clock, in order.
+    fn run_until_clock(&mut self) -> Result<(), VMError> {
+        while self.next_cycle <= self.clock || self.next_tick <= self.clock {
+            if self.next_tick <= self.next_cycle {
+                self.vm.tick_timers();
+                self.next_tick += TIMER_INTERVAL;
+            } else {
+                self.vm.cycle()?;
+                self.next_cycle += self.cycle_interval;
             }
         }
+        self.handle_events();
+        Ok(())
+    }
 
+    /// Run up to and including the next timer tick, i.e. one 60Hz frame.
+    fn advance_frame(&mut self) -> Result<(), VMError> {
+        self.clock = self.next_tick;
+        self.run_until_clock()?;
+        self.request_redraw();
         Ok(())
     }
 
+    fn speed(&self) -> f64 {
+        let ff = if self.fast_forward { FAST_FORWARD } else { 1.0 };
+        SPEEDS[self.speed_index] * ff
+    }
+
+    fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn title(&self) -> String {
+        let name = self.rom_path.file_name().unwrap_or_default();
+        let mut title = format!("Chip-8 - {}", name.to_string_lossy());
+        if self.paused {
+            title.push_str(" - Paused (F6 next frame)");
+        } else if self.speed() != 1.0 {
+            title.push_str(&format!(" - {}x", self.speed()));
+            if self.fast_forward {
+                title.push_str(" fast forward");
+            }
+        }
+        title
+    }
+
+    fn update_title(&self) {
+        if let Some(window) = &self.window {
+            window.set_title(&self.title());
+        }
+    }
+
+    fn handle_events(&mut self) {
+        while let Some(event) = self.vm.poll_event() {
+            match event {
+                Event::DisplayCleared | Event::DisplayDrawn(_) => self.display_changed = true,
+                Event::Halted { pc } => info!("program halted at {:#X}", pc),
+                _ => {}
+            }
+        }
+    }
+
     fn draw_frame(&mut self) {
+        self.display_changed = false;
         if let Some(pixels) = &mut self.frame_buffer {
-            let vm_frame = self.vm.get_frame_buffer();
-
-            // Each pixel is 4 bytes (rbga) so we chunk and map from bool buf -> pixels.
-            for (i, pixel) in pixels.frame_mut().chunks_exact_mut(4).enumerate() {
-                let vm_pixel = vm_frame[i];
-                // purple on black background
-                let rgba = if vm_pixel {
-                    [0x5e, 0x48, 0xe8, 0xff]
-                } else {
-                    [0x0, 0x0, 0x0, 0xff]
-                };
-                pixel.copy_from_slice(&rgba);
-            }
+            // Persistence smooths out XOR flicker, giving an intensity per pixel.
+            let intensity = self.persistence.apply(self.vm.get_frame_buffer());
+
+            // The renderer scales and colors the frame into rgba matching the pixels buffer.
+            let rgba = self.renderer.render_intensity(intensity);
+            pixels.frame_mut().copy_from_slice(rgba);
             pixels.render().unwrap();
         }
     }
 
+    /// Handle emulator hotkeys, returning false for keys meant for the VM.
+    /// F5 pauses, F6 advances a frame while paused, F7/F8 step the speed
+    /// down/up and holding Tab fast-forwards, unless Tab is bound to a
+    /// CHIP-8 key. F9 resets, F10 reloads the ROM from disk and Ctrl+O opens
+    /// another one.
+    fn handle_control(&mut self, code: KeyCode, is_pressed: bool) -> Result<bool, VMError> {
+        match code {
+            KeyCode::Tab if !self.key_bindings.contains_key(&code) => {
+                self.fast_forward = is_pressed;
+            }
+            KeyCode::KeyO if self.modifiers.control_key() => {
+                if is_pressed {
+                    if let Some(path) = self.pick_rom() {
+                        self.open_or_report(path);
+                    }
+                }
+            }
+            KeyCode::F5 | KeyCode::F6 | KeyCode::F7 | KeyCode::F8 | KeyCode::F9 | KeyCode::F10
+                if !is_pressed => {}
+            KeyCode::F5 => self.paused = !self.paused,
+            KeyCode::F9 => self.reset(),
+            KeyCode::F10 => self.open_or_report(self.rom_path.clone()),
+            KeyCode::F6 if self.paused => self.advance_frame()?,
+            KeyCode::F7 => self.speed_index = self.speed_index.saturating_sub(1),
+            KeyCode::F8 => self.speed_index = (self.speed_index + 1).min(SPEEDS.len() - 1),
+            KeyCode::F6 => {}
+            _ => return Ok(false),
+        }
+        self.update_title();
+        Ok(true)
+    }
+
     fn handle_key(&mut self, code: KeyCode, is_pressed: bool) {
         // Map key codes to computer-keyboard-friendly codes.
         // [1, 2, 3, 4]
         // [Q, W, E, R]
         // [A, S, D, F]
         // [Z, X, C, V]
-        let key_code: u8 = {
+        let key_code: u8 = if let Some(key) = self.key_bindings.get(&code) {
+            *key
+        } else {
             match code {
                 KeyCode::Digit1 => 0x1,
                 KeyCode::Digit2 => 0x2,
@@ -147,23 +467,76 @@ impl Emulator {
     }
 }
 
+/// Read the ROM at `path`. Octo carts (`.gif`) hold source code, which is
+/// assembled here and run with the cart's options.
+fn read_program(path: &str) -> Result<(Vec<u8>, Option<CartOptions>), Box<dyn Error>> {
+    let path = Path::new(path);
+    let bytes = fs::read(path)
+        .map_err(|e| VMError::RomLoadFailure(RomLoadError::io(path.display(), &e)))?;
+    if !path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+    {
+        return Ok((bytes, None));
+    }
+
+    let cart = Cartridge::decode(&bytes)?;
+    Ok((cart.assemble()?, Some(cart.options)))
+}
+
+/// Keyboard keys by their winit names, for key bindings in config files.
+fn key_code(name: &str) -> Option<KeyCode> {
+    macro_rules! key_codes {
+        ($($code:ident)*) => {
+            match name {
+                $(stringify!($code) => Some(KeyCode::$code),)*
+                _ => None,
+            }
+        };
+    }
+    key_codes!(
+        KeyA KeyB KeyC KeyD KeyE KeyF KeyG KeyH KeyI KeyJ KeyK KeyL KeyM
+        KeyN KeyO KeyP KeyQ KeyR KeyS KeyT KeyU KeyV KeyW KeyX KeyY KeyZ
+        Digit0 Digit1 Digit2 Digit3 Digit4 Digit5 Digit6 Digit7 Digit8 Digit9
+        Numpad0 Numpad1 Numpad2 Numpad3 Numpad4 Numpad5 Numpad6 Numpad7 Numpad8 Numpad9
+        NumpadAdd NumpadSubtract NumpadMultiply NumpadDivide NumpadDecimal NumpadEnter
+        ArrowUp ArrowDown ArrowLeft ArrowRight
+        Space Enter Tab Backspace ShiftLeft ShiftRight ControlLeft ControlRight AltLeft AltRight
+        Comma Period Slash Semicolon Quote BracketLeft BracketRight Minus Equal Backquote Backslash
+    )
+}
+
+// Keyboard keys for the named bindings in the ROM database.
+fn binding_key_code(name: &str) -> Option<KeyCode> {
+    match name {
+        "up" => Some(KeyCode::ArrowUp),
+        "down" => Some(KeyCode::ArrowDown),
+        "left" => Some(KeyCode::ArrowLeft),
+        "right" => Some(KeyCode::ArrowRight),
+        "a" => Some(KeyCode::Space),
+        "b" => Some(KeyCode::Enter),
+        _ => None,
+    }
+}
+
 impl ApplicationHandler for Emulator {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let size = LogicalSize::new(
+            Display::WIDTH as u32 * self.scale,
+            Display::HEIGHT as u32 * self.scale,
+        );
         let window_attributes = Window::default_attributes()
-            .with_title(format!("Chip-8 - {}", self.rom_name))
-            .with_inner_size(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT))
-            .with_min_inner_size(LogicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT));
+            .with_title(self.title())
+            .with_inner_size(size)
+            .with_min_inner_size(size)
+            .with_fullscreen(self.fullscreen.then_some(Fullscreen::Borderless(None)));
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
         let fb = {
             let window_size = window.inner_size();
             let surface_texture =
                 SurfaceTexture::new(window_size.width, window_size.height, window.clone());
-            Pixels::new(
-                Display::WIDTH as u32,
-                Display::HEIGHT as u32,
-                surface_texture,
-            )
-            .unwrap()
+            let (width, height) = self.renderer.size();
+            Pixels::new(width as u32, height as u32, surface_texture).unwrap()
         };
 
         self.window = Some(window.clone());
@@ -186,16 +559,25 @@ impl ApplicationHandler for Emulator {
                     if let Err(err) = pixels.resize_surface(size.width, size.height) {
                         println!("pixels.resize_surface: {:?}", err);
                         event_loop.exit();
-                        return;
                     }
                 }
             }
             WindowEvent::RedrawRequested => {
                 self.draw_frame();
             }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::DroppedFile(path) => self.open_or_report(path),
             WindowEvent::KeyboardInput { event, .. } => {
                 if let PhysicalKey::Code(code) = event.physical_key {
-                    self.handle_key(code, event.state.is_pressed());
+                    let is_pressed = event.state.is_pressed();
+                    match self.handle_control(code, is_pressed) {
+                        Ok(true) => {}
+                        Ok(false) => self.handle_key(code, is_pressed),
+                        Err(err) => {
+                            println!("failed to run cycle: {}", err);
+                            event_loop.exit();
+                        }
+                    }
                 }
             }
             _ => {}
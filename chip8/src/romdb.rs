@@ -0,0 +1,358 @@
+//! Per-ROM settings keyed by SHA-1, read from `programs.json` files in the
+//! format of the community chip-8-database
+//! (https://github.com/chip-8/chip-8-database).
+//!
+//! `roms.json` is built in. It holds the entries of the community
+//! `programs.json` that have a platform we can emulate, plus the bundled
+//! test ROMs. Refresh it from a downloaded copy with
+//! `CHIP8_ROMDB_SOURCE=programs.json cargo test update_bundled -- --ignored`.
+//! `--rom-db` layers another `programs.json` on top.
+
+use chip8_core::palette::{parse_color, Palette};
+use chip8_core::platform::Platform;
+use chip8_core::quirks::Quirks;
+use chip8_core::rom::RomInfo;
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const BUNDLED: &str = include_str!("../roms.json");
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    description: Option<String>,
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, QuirkOverrides>,
+    tickrate: Option<u32>,
+    colors: Option<Colors>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct QuirkOverrides {
+    shift: Option<bool>,
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>,
+    jump: Option<bool>,
+    vblank: Option<bool>,
+    logic: Option<bool>,
+}
+
+impl QuirkOverrides {
+    fn apply(&self, mut quirks: Quirks) -> Quirks {
+        let fields = [
+            (self.shift, &mut quirks.shift),
+            (
+                self.memory_increment_by_x,
+                &mut quirks.memory_increment_by_x,
+            ),
+            (
+                self.memory_leave_i_unchanged,
+                &mut quirks.memory_leave_i_unchanged,
+            ),
+            (self.wrap, &mut quirks.wrap),
+            (self.jump, &mut quirks.jump),
+            (self.vblank, &mut quirks.vblank),
+            (self.logic, &mut quirks.logic),
+        ];
+        for (value, field) in fields {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+        quirks
+    }
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+/// Settings for one ROM, ready to apply to the VM and frontend.
+#[derive(Debug, Clone)]
+pub struct RomSettings {
+    pub title: String,
+    pub description: Option<String>,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Instructions per 60Hz frame.
+    pub tickrate: Option<u32>,
+    pub palette: Option<Palette>,
+    /// Extra bindings from names like `up` or `a` to CHIP-8 keys.
+    pub keys: HashMap<String, u8>,
+}
+
+#[derive(Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomSettings>,
+}
+
+impl RomDatabase {
+    pub fn bundled() -> RomDatabase {
+        let mut db = RomDatabase::default();
+        db.add_json(BUNDLED)
+            .expect("bundled ROM database is valid JSON");
+        db
+    }
+
+    /// Add the programs in a chip-8-database `programs.json`, replacing any
+    /// existing entries with the same hash.
+    pub fn add_json(&mut self, json: &str) -> Result<(), serde_json::Error> {
+        let programs: Vec<Program> = serde_json::from_str(json)?;
+        for program in programs {
+            for (sha1, rom) in program.roms {
+                match settings(&program.title, program.description.clone(), rom) {
+                    Some(settings) => {
+                        self.roms.insert(sha1.to_lowercase(), settings);
+                    }
+                    None => warn!("skipping {}: no supported platform", program.title),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn lookup(&self, rom: &RomInfo) -> Option<&RomSettings> {
+        self.roms.get(&rom.sha1_hex().to_string())
+    }
+}
+
+fn settings(title: &str, description: Option<String>, rom: RomEntry) -> Option<RomSettings> {
+    // Platforms are listed best first, use the first one we can emulate.
+    let (id, platform, quirks) = rom
+        .platforms
+        .iter()
+        .find_map(|id| platform_for_id(id).map(|(p, q)| (id, p, q)))?;
+    let quirks = rom
+        .quirky_platforms
+        .get(id)
+        .map_or(quirks, |overrides| overrides.apply(quirks));
+
+    let palette = rom.colors.and_then(|colors| {
+        let mut palette = Palette::default();
+        for (slot, color) in palette.colors.iter_mut().zip(&colors.pixels) {
+            *slot = parse_color(color)?;
+        }
+        Some(palette)
+    });
+
+    Some(RomSettings {
+        title: title.to_string(),
+        description,
+        platform,
+        quirks,
+        tickrate: rom.tickrate,
+        palette,
+        keys: rom.keys,
+    })
+}
+
+/// Map a chip-8-database platform id to a platform and its quirks.
+fn platform_for_id(id: &str) -> Option<(Platform, Quirks)> {
+    let platform = match id {
+        "originalChip8" | "hybridVIP" => Platform::CosmacVip,
+        // CHIP-8 as most modern interpreters run it, without VIP timing quirks.
+        "modernChip8" => {
+            let quirks = Quirks {
+                vblank: false,
+                logic: false,
+                ..Quirks::VIP
+            };
+            return Some((Platform::CosmacVip, quirks));
+        }
+        "chip48" => Platform::Chip48,
+        "superchip1" | "superchip" => Platform::SuperChip,
+        _ => return None,
+    };
+    Some((platform, platform.quirks()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    fn db_with(entry: &str) -> RomDatabase {
+        let sha1 = RomInfo::new(&ROM).sha1_hex().to_string().to_uppercase();
+        let json = format!(
+            r#"[{{"title": "Test", "description": "A test", "roms": {{"{}": {}}}}}]"#,
+            sha1, entry
+        );
+        let mut db = RomDatabase::default();
+        db.add_json(&json).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_lookup() {
+        let db = db_with(
+            r##"{
+                "platforms": ["xochip", "superchip"],
+                "tickrate": 30,
+                "colors": {"pixels": ["#000000", "#FF8000"]},
+                "keys": {"up": 5, "a": 6}
+            }"##,
+        );
+        let settings = db.lookup(&RomInfo::new(&ROM)).unwrap();
+        assert_eq!(settings.title, "Test");
+        assert_eq!(settings.description.as_deref(), Some("A test"));
+        // xochip isn't supported, so the next platform is used.
+        assert_eq!(settings.platform, Platform::SuperChip);
+        assert_eq!(settings.quirks, Platform::SuperChip.quirks());
+        assert_eq!(settings.tickrate, Some(30));
+        assert_eq!(
+            settings.palette.unwrap().colors[1],
+            [0xFF, 0x80, 0x00, 0xFF]
+        );
+        assert_eq!(settings.keys["up"], 5);
+        assert!(db.lookup(&RomInfo::new(&[0x00, 0xE0])).is_none());
+    }
+
+    #[test]
+    fn test_quirk_overrides() {
+        let db = db_with(
+            r#"{
+                "platforms": ["modernChip8"],
+                "quirkyPlatforms": {"modernChip8": {"memoryIncrementByX": true, "wrap": true}}
+            }"#,
+        );
+        let quirks = db.lookup(&RomInfo::new(&ROM)).unwrap().quirks;
+        assert!(quirks.memory_increment_by_x);
+        assert!(quirks.wrap);
+        assert!(!quirks.vblank);
+        assert!(!quirks.logic);
+    }
+
+    #[test]
+    fn test_unsupported_platform_skipped() {
+        let db = db_with(r#"{"platforms": ["megachip8"]}"#);
+        assert!(db.lookup(&RomInfo::new(&ROM)).is_none());
+    }
+
+    #[test]
+    fn test_add_json_errors() {
+        let mut db = RomDatabase::default();
+        assert!(db.add_json("not json").is_err());
+        assert!(db.add_json(r#"[{"roms": {}}]"#).is_err());
+        assert!(db
+            .add_json(r#"[{"title": "x", "roms": {"ab": {"tickrate": "fast"}}}]"#)
+            .is_err());
+    }
+
+    // The entries of `source` with a supported platform, with only the fields
+    // `add_json` reads, followed by the programs of `local` that `source`
+    // doesn't have.
+    fn bundle(source: &str, local: &str) -> serde_json::Result<String> {
+        use serde_json::{Map, Value};
+
+        const PROGRAM_FIELDS: [&str; 2] = ["title", "description"];
+        const ROM_FIELDS: [&str; 5] =
+            ["platforms", "quirkyPlatforms", "tickrate", "colors", "keys"];
+        let keep = |object: &Map<String, Value>, fields: &[&str]| -> Map<String, Value> {
+            fields
+                .iter()
+                .filter_map(|&field| Some((field.to_string(), object.get(field)?.clone())))
+                .collect()
+        };
+        let supported = |rom: &Value| {
+            rom["platforms"].as_array().is_some_and(|ids| {
+                ids.iter()
+                    .filter_map(Value::as_str)
+                    .any(|id| platform_for_id(id).is_some())
+            })
+        };
+
+        let mut programs = Vec::new();
+        let mut hashes = std::collections::HashSet::new();
+        for program in serde_json::from_str::<Vec<Map<String, Value>>>(source)? {
+            let roms: Map<String, Value> = program["roms"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(_, rom)| supported(rom))
+                .filter_map(|(sha1, rom)| {
+                    Some((
+                        sha1.to_lowercase(),
+                        Value::Object(keep(rom.as_object()?, &ROM_FIELDS)),
+                    ))
+                })
+                .collect();
+            if roms.is_empty() {
+                continue;
+            }
+            hashes.extend(roms.keys().cloned());
+            let mut program = keep(&program, &PROGRAM_FIELDS);
+            program.insert("roms".to_string(), Value::Object(roms));
+            programs.push(Value::Object(program));
+        }
+        for program in serde_json::from_str::<Vec<Value>>(local)? {
+            let known = program["roms"].as_object().is_some_and(|roms| {
+                roms.keys()
+                    .any(|sha1| hashes.contains(&sha1.to_lowercase()))
+            });
+            if !known {
+                programs.push(program);
+            }
+        }
+        Ok(serde_json::to_string_pretty(&programs)? + "\n")
+    }
+
+    #[test]
+    fn test_bundle() {
+        let source = r#"[
+            {"title": "Pong", "authors": ["x"], "roms": {
+                "AA": {"file": "pong.ch8", "platforms": ["originalChip8"], "tickrate": 9},
+                "bb": {"platforms": ["megachip8"]}
+            }},
+            {"title": "Mega", "roms": {"cc": {"platforms": ["megachip8"]}}},
+            {"title": "Test", "roms": {"dd": {"platforms": ["superchip"]}}}
+        ]"#;
+        let local = r#"[
+            {"title": "Old test", "roms": {"dd": {"platforms": ["chip48"]}}},
+            {"title": "Local", "roms": {"ee": {"platforms": ["chip48"]}}}
+        ]"#;
+        let bundled: serde_json::Value =
+            serde_json::from_str(&bundle(source, local).unwrap()).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"[
+                {"title": "Pong", "roms": {"aa": {"platforms": ["originalChip8"], "tickrate": 9}}},
+                {"title": "Test", "roms": {"dd": {"platforms": ["superchip"]}}},
+                {"title": "Local", "roms": {"ee": {"platforms": ["chip48"]}}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(bundled, expected);
+    }
+
+    #[test]
+    #[ignore = "needs CHIP8_ROMDB_SOURCE pointing at the community programs.json"]
+    fn update_bundled() {
+        let source = std::env::var("CHIP8_ROMDB_SOURCE").expect("CHIP8_ROMDB_SOURCE isn't set");
+        let source = std::fs::read_to_string(source).unwrap();
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/roms.json");
+        let bundled = bundle(&source, BUNDLED).unwrap();
+        RomDatabase::default().add_json(&bundled).unwrap();
+        std::fs::write(path, bundled).unwrap();
+    }
+
+    #[test]
+    fn test_bundled() {
+        let rom = std::fs::read("../bin/test-roms/3-corax+.ch8").unwrap();
+        assert!(RomDatabase::bundled().lookup(&RomInfo::new(&rom)).is_some());
+    }
+}